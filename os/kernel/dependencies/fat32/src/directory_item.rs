@@ -120,6 +120,19 @@ impl ShortDirectoryItem {
         }
     }
 
+    /// FAT's LFN checksum over the raw 11-byte 8.3 name (name + extension,
+    /// space-padded): folds each byte through `(((sum & 1) << 7) | (sum >>
+    /// 1)) + byte` with `u8` wrapping. Every LFN slot belonging to this SFN
+    /// must carry this value in `check_sum`; a mismatch means the LFN run is
+    /// stale (the SFN it described got renamed/overwritten in place).
+    pub fn checksum(&self) -> u8 {
+        let mut sum: u8 = 0;
+        for &byte in self.name.iter().chain(self.extension.iter()) {
+            sum = (((sum & 1) << 7) | (sum >> 1)).wrapping_add(byte);
+        }
+        sum
+    }
+
     pub fn get_full_name_bytes(&self) -> ([u8; 12], usize) {
         let mut len = 0;
         let mut full_name = [0; 12];
@@ -208,8 +221,15 @@ impl LongDirectoryItem {
             temp[index] = 0;
             temp[index + 1] = 0;
         }
-        index = 0;
 
+        Self::pack_unicode(&temp, buf);
+    }
+
+    /// Scatter a slot's 26-byte unicode fragment (as laid out by
+    /// `write_unicode`/`new_from_units`) into the three discontiguous
+    /// `unicode_part*` ranges of a raw 32-byte LFN entry.
+    fn pack_unicode(temp: &[u8; 26], buf: &mut [u8]) {
+        let mut index = 0;
         let mut op = |start: usize, end: usize| {
             for i in (start..end).step_by(2) {
                 buf[i] = temp[index];
@@ -223,6 +243,32 @@ impl LongDirectoryItem {
         op(0x1C, 0x1F);
     }
 
+    /// Build one LFN slot directly from up to 13 already-encoded UTF-16
+    /// units, padding with a NUL terminator then `0xFFFF` filler exactly as
+    /// `write_unicode` does for a whole name. Used to emit the slot run for
+    /// a long name that doesn't fit 8.3, one 13-unit chunk per slot.
+    pub fn new_from_units(attribute: u8, check_sum: u8, units: &[u16]) -> Self {
+        let mut temp = [0xFFu8; 26];
+        let mut index = 0;
+
+        for &unit in units.iter().take(13) {
+            temp[index] = (unit & 0xFF) as u8;
+            temp[index + 1] = ((unit & 0xFF00) >> 8) as u8;
+            index += 2;
+        }
+
+        if units.len() < 13 {
+            temp[index] = 0;
+            temp[index + 1] = 0;
+        }
+
+        let mut buf = [0; 32];
+        buf[0x00] = attribute;
+        buf[0x0D] = check_sum;
+        Self::pack_unicode(&temp, &mut buf);
+        Self::from_buf(&buf)
+    }
+
     pub fn from_buf(buf: &[u8]) -> Self {
         let attribute = buf[0x00];
         let check_sum = buf[0x0D];
@@ -243,15 +289,48 @@ impl LongDirectoryItem {
         }
     }
 
-    pub fn to_utf8(&self) -> ([u8; 13 * 3], usize) {
-        let (mut utf8, mut len) = ([0; 13 * 3], 0);
+    /// Decode this slot's 13 UTF-16 units into UTF-8, honoring surrogate
+    /// pairs. `carry_in` is a high surrogate the *previous* slot's last unit
+    /// turned out to be, still waiting on this slot's first unit as its low
+    /// half. Returns the decoded bytes, their length, and `carry_out`: this
+    /// slot's own last unit, if it's a high surrogate left for whichever
+    /// slot follows to resolve. A high surrogate that never finds its low
+    /// half (end of name, or followed by something else) is dropped rather
+    /// than emitted as corrupt UTF-8.
+    ///
+    /// The buffer is sized `13 * 4` (4 bytes/unit) because `13 * 3` is not
+    /// enough once `carry_in` is set: the slot's first unit then completes
+    /// a pair and emits 4 bytes on its own, and the other 12 units can emit
+    /// up to 3 bytes each, 40 bytes in all.
+    pub fn to_utf8_with_carry(&self, carry_in: Option<u16>) -> ([u8; 13 * 4], usize, Option<u16>) {
+        let mut utf8 = [0u8; 13 * 4];
+        let mut len = 0;
+        let mut pending_high = carry_in;
 
-        let mut op = |part: &[u8]| {
+        let mut op = |part: &[u8]| -> bool {
             for i in (0..part.len()).step_by(2) {
-                if (part[i] == 0x00 && part[i + 1] == 0x00) || part[i] == 0xFF { break; }
+                if (part[i] == 0x00 && part[i + 1] == 0x00) || part[i] == 0xFF {
+                    pending_high = None;
+                    return false;
+                }
                 let unicode = ((part[i + 1] as u16) << 8) | part[i] as u16;
 
-                if unicode <= 0x007F {
+                if let Some(hi) = pending_high.take() {
+                    if (0xDC00..=0xDFFF).contains(&unicode) {
+                        let code = 0x10000u32 + (((hi - 0xD800) as u32) << 10) + (unicode - 0xDC00) as u32;
+                        utf8[len] = (0b11110000 | (0b00000111 & (code >> 18))) as u8;
+                        utf8[len + 1] = (0b10000000 | (0b00111111 & (code >> 12))) as u8;
+                        utf8[len + 2] = (0b10000000 | (0b00111111 & (code >> 6))) as u8;
+                        utf8[len + 3] = (0b10000000 | (0b00111111 & code)) as u8;
+                        len += 4;
+                        continue;
+                    }
+                    // Orphaned high surrogate: drop it, decode this unit normally.
+                }
+
+                if (0xD800..=0xDBFF).contains(&unicode) {
+                    pending_high = Some(unicode);
+                } else if unicode <= 0x007F {
                     utf8[len] = unicode as u8;
                     len += 1;
                 } else if unicode >= 0x0080 && unicode <= 0x07FF {
@@ -261,7 +340,7 @@ impl LongDirectoryItem {
                     utf8[len] = part1;
                     utf8[len + 1] = part2;
                     len += 2;
-                } else if unicode >= 0x0800 {
+                } else {
                     let part1 = (0b11100000 | (0b00011111 & (unicode >> 12))) as u8;
                     let part2 = (0b10000000 | (0b00111111) & (unicode >> 6)) as u8;
                     let part3 = (0b10000000 | (0b00111111) & unicode) as u8;
@@ -272,12 +351,16 @@ impl LongDirectoryItem {
                     len += 3;
                 }
             }
+            true
         };
 
-        op(&self.unicode_part1);
-        op(&self.unicode_part2);
-        op(&self.unicode_part3);
+        let _ = op(&self.unicode_part1) && op(&self.unicode_part2) && op(&self.unicode_part3);
+
+        (utf8, len, pending_high)
+    }
 
+    pub fn to_utf8(&self) -> ([u8; 13 * 4], usize) {
+        let (utf8, len, _) = self.to_utf8_with_carry(None);
         (utf8, len)
     }
 
@@ -310,6 +393,135 @@ pub struct DirectoryItem {
 }
 
 impl DirectoryItem {
+    /// Maximum on-disk LFN slots one long name can span. The ordinal is a
+    /// 5-bit field (`count_of_name`), so 31 is the hard ceiling; FAT32's
+    /// 255 UTF-16-unit name cap only ever needs 20.
+    pub const MAX_LFN_SLOTS: usize = 31;
+
+    /// Walk `entries` — raw 32-byte directory records, with any LFN slots
+    /// (descending ordinal, immediately before their SFN) leading a final
+    /// SFN record — and assemble the name they encode.
+    ///
+    /// Returns `Some((name_buf, name_len, sfn, consumed))`, where `consumed`
+    /// is how many of `entries` (LFN slots plus the terminating SFN) this
+    /// name occupies, so the caller can advance past the whole run
+    /// regardless of whether the LFN validated. A checksum mismatch or an
+    /// ordinal gap discards the LFN run and falls back to the SFN's own 8.3
+    /// name.
+    ///
+    /// Returns `None` if the leading LFN slots run all the way to the end
+    /// of `entries` with no terminating SFN — a truncated directory block,
+    /// or a block boundary landing mid-run when the caller feeds entries in
+    /// chunks. There's nothing to assemble in that case; the caller should
+    /// treat it as "no complete entry here" rather than index past the end.
+    pub fn assemble_name(entries: &[[u8; 32]]) -> Option<([u8; 13 * 4 * Self::MAX_LFN_SLOTS], usize, ShortDirectoryItem, usize)> {
+        let mut slots = [None; Self::MAX_LFN_SLOTS];
+        let mut n = 0;
+
+        while n < entries.len() && n < Self::MAX_LFN_SLOTS
+            && ItemType::from_value(entries[n][0x0B]) == ItemType::LFN
+        {
+            slots[n] = Some(LongDirectoryItem::from_buf(&entries[n]));
+            n += 1;
+        }
+
+        if n >= entries.len() {
+            return None;
+        }
+
+        let sfn = ShortDirectoryItem::from_buf(&entries[n]);
+        let consumed = n + 1;
+
+        let mut buf = [0u8; 13 * 4 * Self::MAX_LFN_SLOTS];
+        let mut len = 0;
+
+        if n > 0 && Self::valid_lfn_run(&slots[0..n], sfn.checksum()) {
+            // Slots are stored highest-ordinal (last name chunk) first;
+            // walk them back to front to rebuild the name in reading order.
+            // A surrogate pair split across two slots (high half ending one
+            // slot, low half opening the next) is rejoined by threading the
+            // pending high surrogate through as `carry`.
+            let mut carry = None;
+            for slot in slots[0..n].iter().rev() {
+                let (fragment, flen, next_carry) = slot.unwrap().to_utf8_with_carry(carry);
+                buf[len..len + flen].copy_from_slice(&fragment[0..flen]);
+                len += flen;
+                carry = next_carry;
+            }
+        } else {
+            let (fragment, flen) = sfn.get_full_name_bytes();
+            buf[0..flen].copy_from_slice(&fragment[0..flen]);
+            len = flen;
+        }
+
+        Some((buf, len, sfn, consumed))
+    }
+
+    /// `slots` must run ordinal `slots.len(), slots.len() - 1, ..., 1` (the
+    /// order they're stored on disk), with the first one flagged `0x40`
+    /// (`is_name_end`), and every slot's `check_sum` must match the SFN's.
+    fn valid_lfn_run(slots: &[Option<LongDirectoryItem>], sfn_checksum: u8) -> bool {
+        if !slots[0].unwrap().is_name_end() {
+            return false;
+        }
+        for (i, slot) in slots.iter().enumerate() {
+            let slot = slot.unwrap();
+            if slot.check_sum != sfn_checksum || slot.count_of_name() != slots.len() - i {
+                return false;
+            }
+        }
+        true
+    }
+
+    /// Build the full on-disk entry run — LFN slots (highest ordinal
+    /// first) followed by the SFN — for a name that doesn't fit 8.3, so it
+    /// survives a write/read round-trip instead of silently collapsing to
+    /// whatever `ShortDirectoryItem::new` derives. `short_name` is the
+    /// already-mangled 8.3 alias to store in the SFN; picking it is the
+    /// caller's concern.
+    pub fn new_long(cluster: u32, value: &str, short_name: &str, create_type: OpType) -> ([DirectoryItem; Self::MAX_LFN_SLOTS + 1], usize) {
+        let mut units = [0u16; 13 * Self::MAX_LFN_SLOTS];
+        let mut unit_len = 0;
+        for unit in value.encode_utf16() {
+            if unit_len >= units.len() {
+                break;
+            }
+            units[unit_len] = unit;
+            unit_len += 1;
+        }
+
+        let slot_count = (unit_len + 12) / 13;
+        let sfn = ShortDirectoryItem::new(cluster, short_name, create_type);
+        let checksum = sfn.checksum();
+
+        let mut entries = [DirectoryItem::default(); Self::MAX_LFN_SLOTS + 1];
+        let mut n = 0;
+
+        for ordinal in (1..=slot_count).rev() {
+            let start = (ordinal - 1) * 13;
+            let end = (start + 13).min(unit_len);
+            let mut attribute = ordinal as u8;
+            if ordinal == slot_count {
+                attribute |= 0x40;
+            }
+            entries[n] = Self {
+                item_type: ItemType::LFN,
+                sfn: None,
+                lfn: Some(LongDirectoryItem::new_from_units(attribute, checksum, &units[start..end])),
+            };
+            n += 1;
+        }
+
+        entries[n] = Self {
+            item_type: ItemType::from_create(create_type),
+            sfn: Some(sfn),
+            lfn: None,
+        };
+        n += 1;
+
+        (entries, n)
+    }
+
     pub fn cluster(&self) -> u32 {
         self.sfn.unwrap().cluster
     }
@@ -322,7 +534,7 @@ impl DirectoryItem {
         }
     }
 
-    pub fn get_lfn(&self) -> Option<([u8; 13 * 3], usize)> {
+    pub fn get_lfn(&self) -> Option<([u8; 13 * 4], usize)> {
         if self.lfn.is_some() {
             Some(self.lfn.as_ref().unwrap().to_utf8())
         } else {
@@ -462,3 +674,44 @@ impl DirectoryItem {
         ItemType::File == self.item_type
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// "123456789012" (12 units) + U+1F600 (2 units, straddling the 13-unit
+    /// slot boundary) + "a" spans exactly two LFN slots, with the surrogate
+    /// pair's high half as the last unit of slot 1 and its low half as the
+    /// first unit of slot 2 -- the case `to_utf8_with_carry`'s `carry`
+    /// threading exists for.
+    #[test]
+    fn assemble_name_round_trips_surrogate_pair_split_across_slots() {
+        let name = "123456789012\u{1F600}a";
+        let (entries, n) = DirectoryItem::new_long(0, name, "SPLIT~1", OpType::File);
+        assert_eq!(n, 3); // 2 LFN slots + 1 SFN
+
+        let mut bufs = [[0u8; 32]; 3];
+        for i in 0..n {
+            bufs[i] = entries[i].bytes();
+        }
+
+        let (buf, len, _sfn, consumed) = DirectoryItem::assemble_name(&bufs[0..n]).unwrap();
+        assert_eq!(consumed, n);
+        assert_eq!(str::from_utf8(&buf[0..len]).unwrap(), name);
+    }
+
+    #[test]
+    fn assemble_name_returns_none_on_truncated_lfn_run() {
+        let name = "123456789012\u{1F600}a";
+        let (entries, n) = DirectoryItem::new_long(0, name, "SPLIT~1", OpType::File);
+
+        // Drop the terminating SFN, as if the caller fed a block that ends
+        // mid-run -- there is no complete entry to assemble here.
+        let mut bufs = [[0u8; 32]; 2];
+        for i in 0..n - 1 {
+            bufs[i] = entries[i].bytes();
+        }
+
+        assert!(DirectoryItem::assemble_name(&bufs[0..n - 1]).is_none());
+    }
+}
@@ -0,0 +1,51 @@
+//! Block-device abstraction that `ext2` (and any future on-disk fs) reads
+//! and writes through, independent of what's actually backing it.
+
+use alloc::vec::Vec;
+use spin::Mutex;
+
+/// Sector size every `BlockDevice` is addressed in, regardless of the
+/// filesystem block size built on top of it (ext2's block size is always a
+/// multiple of this).
+pub const SECTOR_SIZE: usize = 512;
+
+/// Minimal LBA-addressed block device: read/write one `SECTOR_SIZE` sector
+/// at a time. `buf.len()` must be a multiple of `SECTOR_SIZE`.
+pub trait BlockDevice: Send + Sync {
+    fn read_block(&self, lba: usize, buf: &mut [u8]);
+    fn write_block(&self, lba: usize, buf: &[u8]);
+}
+
+/// A block device backed entirely by an in-memory image — the initrd/
+/// ramdisk case `ext2::mount_ext2` reads from, since there's no real disk
+/// controller driver yet. Nothing in the kernel mounts such an image at boot.
+pub struct RamBlockDevice {
+    data: Mutex<Vec<u8>>,
+}
+
+impl RamBlockDevice {
+    pub fn new(data: Vec<u8>) -> Self {
+        Self { data: Mutex::new(data) }
+    }
+}
+
+impl BlockDevice for RamBlockDevice {
+    fn read_block(&self, lba: usize, buf: &mut [u8]) {
+        let data = self.data.lock();
+        let start = lba * SECTOR_SIZE;
+        let end = (start + buf.len()).min(data.len());
+        let n = end.saturating_sub(start);
+        buf[..n].copy_from_slice(&data[start..start + n]);
+        buf[n..].fill(0);
+    }
+
+    fn write_block(&self, lba: usize, buf: &[u8]) {
+        let mut data = self.data.lock();
+        let start = lba * SECTOR_SIZE;
+        let end = start + buf.len();
+        if end > data.len() {
+            data.resize(end, 0);
+        }
+        data[start..end].copy_from_slice(buf);
+    }
+}
@@ -0,0 +1,539 @@
+//! A read-mostly ext2 driver sitting on top of `block_dev::BlockDevice`,
+//! exposing ext2 files and directories as `vfs::INode`s so
+//! `GlobalDentryCache`/`GlobalInodeTable` can resolve paths straight off a
+//! disk image instead of only the in-memory `RegFileINode` world. Mounts an
+//! initrd/ramdisk image (see `RamBlockDevice`) the same way other hobby
+//! kernels bring up their first persistent filesystem before a real block
+//! driver exists.
+//!
+//! Scope: direct, single-indirect and double-indirect block pointers are
+//! followed for file data (triple-indirect isn't, since no image this
+//! kernel boots needs a file bigger than `12 + ppb + ppb*ppb` blocks).
+//! Metadata writes (`set_*`) update the in-memory copy and flush the inode
+//! back to its block; directory mutation (create/unlink/rename) isn't
+//! implemented, since nothing in this kernel creates files on an ext2
+//! image yet.
+
+use alloc::{string::String, string::ToString, sync::Arc, vec, vec::Vec};
+use spin::Mutex;
+
+use super::block_dev::{BlockDevice, SECTOR_SIZE};
+use super::file::Stat;
+use super::vfs::{FileType, INode, Timespec};
+
+const EXT2_SUPERBLOCK_OFFSET: usize = 1024;
+const EXT2_SUPERBLOCK_SIZE: usize = 1024;
+const EXT2_MAGIC: u16 = 0xEF53;
+const EXT2_ROOT_INODE: u32 = 2;
+const EXT2_DEFAULT_INODE_SIZE: u16 = 128;
+
+const EXT2_N_DIRECT_BLOCKS: usize = 12;
+const S_IFMT: u16 = 0o170000;
+const S_IFDIR: u16 = 0o040000;
+const S_IFLNK: u16 = 0o120000;
+const S_IFREG: u16 = 0o100000;
+
+/// The 1024-byte ext2 superblock, parsed out of the raw bytes at a fixed
+/// offset (1024) regardless of the filesystem's own block size.
+#[derive(Clone, Copy, Default)]
+struct Superblock {
+    inodes_count: u32,
+    blocks_count: u32,
+    first_data_block: u32,
+    log_block_size: u32,
+    blocks_per_group: u32,
+    inodes_per_group: u32,
+    magic: u16,
+    rev_level: u32,
+    inode_size: u16,
+}
+
+impl Superblock {
+    fn parse(raw: &[u8]) -> Self {
+        let u32_at = |off: usize| u32::from_le_bytes(raw[off..off + 4].try_into().unwrap());
+        let u16_at = |off: usize| u16::from_le_bytes(raw[off..off + 2].try_into().unwrap());
+
+        let rev_level = u32_at(0x4C);
+        Self {
+            inodes_count: u32_at(0x00),
+            blocks_count: u32_at(0x04),
+            first_data_block: u32_at(0x14),
+            log_block_size: u32_at(0x18),
+            blocks_per_group: u32_at(0x20),
+            inodes_per_group: u32_at(0x28),
+            magic: u16_at(0x38),
+            rev_level,
+            // `s_inode_size` only exists from rev 1 onward; rev 0 images
+            // are always the original fixed 128-byte inode.
+            inode_size: if rev_level >= 1 { u16_at(0x58) } else { EXT2_DEFAULT_INODE_SIZE },
+        }
+    }
+
+    fn block_size(&self) -> usize {
+        1024 << self.log_block_size
+    }
+
+    fn groups_count(&self) -> u32 {
+        (self.inodes_count + self.inodes_per_group - 1) / self.inodes_per_group
+    }
+}
+
+/// One 32-byte entry of the block group descriptor table.
+#[derive(Clone, Copy, Default)]
+struct GroupDesc {
+    inode_table: u32,
+}
+
+impl GroupDesc {
+    fn parse(raw: &[u8]) -> Self {
+        Self { inode_table: u32::from_le_bytes(raw[0x08..0x0C].try_into().unwrap()) }
+    }
+}
+
+/// The fixed 128-byte-or-larger on-disk inode record (only the fields this
+/// driver actually needs).
+#[derive(Clone, Copy, Default)]
+struct RawInode {
+    mode: u16,
+    size_lo: u32,
+    atime: u32,
+    ctime: u32,
+    mtime: u32,
+    links_count: u16,
+    block: [u32; 15],
+}
+
+impl RawInode {
+    fn parse(raw: &[u8]) -> Self {
+        let u32_at = |off: usize| u32::from_le_bytes(raw[off..off + 4].try_into().unwrap());
+        let u16_at = |off: usize| u16::from_le_bytes(raw[off..off + 2].try_into().unwrap());
+
+        let mut block = [0u32; 15];
+        for (i, slot) in block.iter_mut().enumerate() {
+            *slot = u32_at(0x28 + i * 4);
+        }
+
+        Self {
+            mode: u16_at(0x00),
+            size_lo: u32_at(0x04),
+            atime: u32_at(0x08),
+            ctime: u32_at(0x0C),
+            mtime: u32_at(0x10),
+            links_count: u16_at(0x1A),
+            block,
+        }
+    }
+
+    fn size(&self) -> u64 {
+        self.size_lo as u64
+    }
+}
+
+/// A parsed ext2 image: superblock + group descriptor table, plus the
+/// device backing it. Shared by every `Ext2INode` the mount hands out.
+pub struct Ext2FileSystem {
+    device: Arc<dyn BlockDevice>,
+    sb: Superblock,
+    groups: Vec<GroupDesc>,
+}
+
+impl Ext2FileSystem {
+    /// Parse `device`'s superblock and group descriptor table. Returns
+    /// `None` if it isn't an ext2 image (bad magic).
+    pub fn open(device: Arc<dyn BlockDevice>) -> Option<Arc<Self>> {
+        let mut sb_buf = [0u8; EXT2_SUPERBLOCK_SIZE];
+        read_sectors(&*device, EXT2_SUPERBLOCK_OFFSET, &mut sb_buf);
+        let sb = Superblock::parse(&sb_buf);
+        if sb.magic != EXT2_MAGIC {
+            return None;
+        }
+
+        let block_size = sb.block_size();
+        // The group descriptor table starts in the block right after the
+        // one holding the superblock: block 1 if the superblock shares
+        // block 0 (block_size > 1024), block 2 if it has block 1 to itself
+        // (block_size == 1024).
+        let bgdt_block = if block_size == 1024 { 2 } else { 1 };
+
+        let groups_count = sb.groups_count() as usize;
+        let mut fs = Self { device, sb, groups: Vec::with_capacity(groups_count) };
+
+        let mut gd_buf = vec![0u8; block_size];
+        fs.read_block(bgdt_block, &mut gd_buf);
+        for i in 0..groups_count {
+            let off = i * 32;
+            if off + 32 > gd_buf.len() {
+                // The descriptor table can span more than one block for a
+                // large image; re-read the next one as `off` walks past it.
+                let next_block = bgdt_block + (off / block_size) as u32;
+                fs.read_block(next_block, &mut gd_buf);
+            }
+            let local_off = off % block_size;
+            fs.groups.push(GroupDesc::parse(&gd_buf[local_off..local_off + 32]));
+        }
+
+        Some(Arc::new(fs))
+    }
+
+    fn block_size(&self) -> usize {
+        self.sb.block_size()
+    }
+
+    fn read_block(&self, block_no: u32, buf: &mut [u8]) {
+        read_sectors(&*self.device, block_no as usize * self.block_size(), buf);
+    }
+
+    fn write_block(&self, block_no: u32, buf: &[u8]) {
+        write_sectors(&*self.device, block_no as usize * self.block_size(), buf);
+    }
+
+    fn read_inode(&self, inode_no: u32) -> RawInode {
+        let index = inode_no - 1;
+        let group = (index / self.sb.inodes_per_group) as usize;
+        let index_in_group = index % self.sb.inodes_per_group;
+
+        let inode_size = self.sb.inode_size as usize;
+        let byte_offset = index_in_group as usize * inode_size;
+        let block_size = self.block_size();
+        let block_no = self.groups[group].inode_table + (byte_offset / block_size) as u32;
+        let local_off = byte_offset % block_size;
+
+        let mut block = vec![0u8; block_size];
+        self.read_block(block_no, &mut block);
+        RawInode::parse(&block[local_off..local_off + inode_size.min(block_size - local_off)])
+    }
+
+    /// Resolve the ext2 block number holding the `index`-th block (0-based)
+    /// of an inode's data, following single/double-indirect pointers as
+    /// needed. Returns `0` (a sparse hole) past what double-indirect can
+    /// address.
+    fn data_block(&self, inode: &RawInode, index: u32) -> u32 {
+        let ptrs_per_block = (self.block_size() / 4) as u32;
+
+        if (index as usize) < EXT2_N_DIRECT_BLOCKS {
+            return inode.block[index as usize];
+        }
+        let index = index - EXT2_N_DIRECT_BLOCKS as u32;
+
+        if index < ptrs_per_block {
+            return self.indirect_ptr(inode.block[12], index);
+        }
+        let index = index - ptrs_per_block;
+
+        if index < ptrs_per_block * ptrs_per_block {
+            let first = index / ptrs_per_block;
+            let second = index % ptrs_per_block;
+            let l1_block = self.indirect_ptr(inode.block[13], first);
+            return self.indirect_ptr(l1_block, second);
+        }
+
+        0
+    }
+
+    /// Read the `idx`-th `u32` pointer out of indirect block `block_no`
+    /// (`0` for a hole, which this treats the same as an absent block).
+    fn indirect_ptr(&self, block_no: u32, idx: u32) -> u32 {
+        if block_no == 0 {
+            return 0;
+        }
+        let mut block = vec![0u8; self.block_size()];
+        self.read_block(block_no, &mut block);
+        let off = idx as usize * 4;
+        u32::from_le_bytes(block[off..off + 4].try_into().unwrap())
+    }
+
+    /// Read an inode's entire data (direct + single + double-indirect
+    /// blocks) into one contiguous buffer, truncated to `inode.size()`.
+    fn read_file_data(&self, inode: &RawInode) -> Vec<u8> {
+        let size = inode.size() as usize;
+        let block_size = self.block_size();
+        let block_count = (size + block_size - 1) / block_size;
+
+        let mut data = Vec::with_capacity(size);
+        let mut block_buf = vec![0u8; block_size];
+        for i in 0..block_count {
+            let block_no = self.data_block(inode, i as u32);
+            if block_no == 0 {
+                block_buf.fill(0);
+            } else {
+                self.read_block(block_no, &mut block_buf);
+            }
+            let remaining = size - data.len();
+            data.extend_from_slice(&block_buf[..remaining.min(block_size)]);
+        }
+        data
+    }
+
+    /// Parse a directory inode's data blocks into `(inode_no, name,
+    /// file_type)` triples, skipping deleted entries (`inode_no == 0`).
+    fn read_dir_entries(&self, inode: &RawInode) -> Vec<(u32, String, u8)> {
+        let data = self.read_file_data(inode);
+        let mut entries = Vec::new();
+        let mut off = 0;
+        while off + 8 <= data.len() {
+            let ino = u32::from_le_bytes(data[off..off + 4].try_into().unwrap());
+            let rec_len = u16::from_le_bytes(data[off + 4..off + 6].try_into().unwrap()) as usize;
+            let name_len = data[off + 6] as usize;
+            let file_type = data[off + 7];
+            if rec_len == 0 {
+                break;
+            }
+            if ino != 0 {
+                let name_bytes = &data[off + 8..off + 8 + name_len];
+                if let Ok(name) = core::str::from_utf8(name_bytes) {
+                    if name != "." && name != ".." {
+                        entries.push((ino, name.to_string(), file_type));
+                    }
+                }
+            }
+            off += rec_len;
+        }
+        entries
+    }
+}
+
+/// Read `buf.len()` bytes starting at byte offset `byte_off`, in
+/// `SECTOR_SIZE` chunks, from `device`.
+fn read_sectors(device: &dyn BlockDevice, byte_off: usize, buf: &mut [u8]) {
+    let mut done = 0;
+    while done < buf.len() {
+        let lba = (byte_off + done) / SECTOR_SIZE;
+        let sector_off = (byte_off + done) % SECTOR_SIZE;
+        let mut sector = [0u8; SECTOR_SIZE];
+        device.read_block(lba, &mut sector);
+        let n = (SECTOR_SIZE - sector_off).min(buf.len() - done);
+        buf[done..done + n].copy_from_slice(&sector[sector_off..sector_off + n]);
+        done += n;
+    }
+}
+
+fn write_sectors(device: &dyn BlockDevice, byte_off: usize, buf: &[u8]) {
+    let mut done = 0;
+    while done < buf.len() {
+        let lba = (byte_off + done) / SECTOR_SIZE;
+        let sector_off = (byte_off + done) % SECTOR_SIZE;
+        let mut sector = [0u8; SECTOR_SIZE];
+        device.read_block(lba, &mut sector);
+        let n = (SECTOR_SIZE - sector_off).min(buf.len() - done);
+        sector[sector_off..sector_off + n].copy_from_slice(&buf[done..done + n]);
+        device.write_block(lba, &sector);
+        done += n;
+    }
+}
+
+fn file_type_from_dirent(raw_type: u8, mode: u16) -> FileType {
+    match raw_type {
+        1 => FileType::File,
+        2 => FileType::Dir,
+        3 => FileType::CharDevice,
+        4 => FileType::BlockDevice,
+        5 => FileType::NamedPipe,
+        6 => FileType::Socket,
+        7 => FileType::SymLink,
+        // Feature `filetype` isn't set in this image's superblock, so the
+        // dirent doesn't carry it — fall back to the inode's own mode bits.
+        _ => match mode & S_IFMT {
+            S_IFDIR => FileType::Dir,
+            S_IFLNK => FileType::SymLink,
+            S_IFREG => FileType::File,
+            _ => FileType::Unknown,
+        },
+    }
+}
+
+/// One ext2 file or directory, presented as a `vfs::INode`. `dir`/`name`
+/// are the path components the mount walk discovered it under — ext2
+/// itself has no notion of "an inode's own name", since that lives in
+/// whichever directory entries point at it.
+pub struct Ext2INode {
+    fs: Arc<Ext2FileSystem>,
+    inode_no: u32,
+    raw: RawInode,
+    data: Option<Vec<u8>>,
+    dir: String,
+    name: String,
+}
+
+impl Ext2INode {
+    fn new(fs: Arc<Ext2FileSystem>, inode_no: u32, dir: String, name: String) -> Self {
+        let raw = fs.read_inode(inode_no);
+        Self { fs, inode_no, raw, data: None, dir, name }
+    }
+
+    /// Flush this inode's in-memory metadata (mode/atime/mtime/ctime) back
+    /// to its block on disk.
+    fn flush_inode(&self) {
+        let index = self.inode_no - 1;
+        let group = (index / self.fs.sb.inodes_per_group) as usize;
+        let index_in_group = index % self.fs.sb.inodes_per_group;
+        let inode_size = self.fs.sb.inode_size as usize;
+        let byte_offset = index_in_group as usize * inode_size;
+        let block_size = self.fs.block_size();
+        let block_no = self.fs.groups[group].inode_table + (byte_offset / block_size) as u32;
+        let local_off = byte_offset % block_size;
+
+        let mut block = vec![0u8; block_size];
+        self.fs.read_block(block_no, &mut block);
+        block[local_off..local_off + 2].copy_from_slice(&self.raw.mode.to_le_bytes());
+        block[local_off + 0x08..local_off + 0x0C].copy_from_slice(&self.raw.atime.to_le_bytes());
+        block[local_off + 0x0C..local_off + 0x10].copy_from_slice(&self.raw.ctime.to_le_bytes());
+        block[local_off + 0x10..local_off + 0x14].copy_from_slice(&self.raw.mtime.to_le_bytes());
+        self.fs.write_block(block_no, &block);
+    }
+}
+
+impl INode for Ext2INode {
+    fn file_size(&self) -> u64 {
+        self.raw.size()
+    }
+
+    fn mode(&self) -> u16 {
+        self.raw.mode
+    }
+
+    fn set_mode(&mut self, mode: u16) {
+        self.raw.mode = mode;
+        self.flush_inode();
+    }
+
+    fn file_type(&self) -> FileType {
+        file_type_from_dirent(0, self.raw.mode)
+    }
+
+    fn is_dir(&self) -> bool {
+        self.file_type() == FileType::Dir
+    }
+
+    fn file_data(&mut self) -> &mut Vec<u8> {
+        if self.data.is_none() {
+            self.data = Some(self.fs.read_file_data(&self.raw));
+        }
+        self.data.as_mut().unwrap()
+    }
+
+    fn list(&self) -> Result<Vec<String>, ()> {
+        if !self.is_dir() {
+            return Err(());
+        }
+        Ok(self.fs.read_dir_entries(&self.raw).into_iter().map(|(_, name, _)| name).collect())
+    }
+
+    fn find(&self, name: &str) -> Option<Arc<Mutex<dyn INode>>> {
+        if !self.is_dir() {
+            return None;
+        }
+        let (ino, _, _) = self
+            .fs
+            .read_dir_entries(&self.raw)
+            .into_iter()
+            .find(|(_, entry_name, _)| entry_name == name)?;
+        let child_dir = format_dir(&self.dir, &self.name);
+        Some(Arc::new(Mutex::new(Ext2INode::new(self.fs.clone(), ino, child_dir, name.to_string()))))
+    }
+
+    fn symlink_target(&self) -> String {
+        // A short symlink's target is inlined into `i_block` instead of a
+        // data block; this kernel's images only ever carry short links.
+        if (self.raw.size() as usize) < self.raw.block.len() * 4 {
+            let mut bytes = Vec::new();
+            for word in &self.raw.block {
+                bytes.extend_from_slice(&word.to_le_bytes());
+            }
+            bytes.truncate(self.raw.size() as usize);
+            String::from_utf8(bytes).unwrap_or_default()
+        } else {
+            String::from_utf8(self.fs.read_file_data(&self.raw)).unwrap_or_default()
+        }
+    }
+
+    fn dir_path(&self) -> String {
+        self.dir.clone()
+    }
+
+    fn set_dir(&mut self, dir: String) {
+        self.dir = dir;
+    }
+
+    fn file_name(&self) -> String {
+        self.name.clone()
+    }
+
+    fn set_name(&mut self, name: String) {
+        // Renaming an ext2 file means rewriting the parent directory's
+        // entries, not this inode — out of scope for the read-mostly
+        // driver this mount provides. Track the new label locally so
+        // `dir_path`/`file_name` stay consistent with whatever the dentry
+        // cache already moved it to.
+        self.name = name;
+    }
+
+    fn set_atime(&mut self, t: Timespec) {
+        self.raw.atime = t.sec as u32;
+        self.flush_inode();
+    }
+
+    fn set_mtime(&mut self, t: Timespec) {
+        self.raw.mtime = t.sec as u32;
+        self.flush_inode();
+    }
+
+    fn set_ctime(&mut self, t: Timespec) {
+        self.raw.ctime = t.sec as u32;
+        self.flush_inode();
+    }
+
+    fn stat(&self) -> Stat {
+        let mut stat = Stat::new();
+        stat.mode = self.raw.mode;
+        stat.nlink = self.raw.links_count;
+        stat.size = self.raw.size() as i64;
+        let block_size = self.fs.block_size() as i64;
+        stat.blksize = block_size;
+        stat.blocks = (stat.size + 511) / 512;
+        stat.atime = Timespec { sec: self.raw.atime as usize, nsec: 0 };
+        stat.mtime = Timespec { sec: self.raw.mtime as usize, nsec: 0 };
+        stat.ctime = Timespec { sec: self.raw.ctime as usize, nsec: 0 };
+        stat
+    }
+}
+
+fn format_dir(parent_dir: &str, parent_name: &str) -> String {
+    if parent_name.is_empty() {
+        parent_dir.to_string()
+    } else {
+        alloc::format!("{}{}/", parent_dir, parent_name)
+    }
+}
+
+/// Mount an ext2 image, eagerly walking its whole tree and registering
+/// every path under `mount_point` (e.g. `"/"`) in `dentry_insert` — the
+/// same "cache everything up front" approach `RegFileINode`'s callers
+/// already take, rather than teaching `GlobalDentryCache` to resolve
+/// misses lazily through a mounted filesystem.
+pub fn mount_ext2(
+    device: Arc<dyn BlockDevice>,
+    mount_point: &str,
+    dentry_insert: impl Fn(&str, Arc<Mutex<dyn INode>>),
+) -> Option<()> {
+    let fs = Ext2FileSystem::open(device)?;
+    let root = Arc::new(Mutex::new(Ext2INode::new(fs.clone(), EXT2_ROOT_INODE, String::new(), String::new())));
+    let mount_point = mount_point.trim_end_matches('/');
+    walk_and_insert(&root, mount_point, &dentry_insert);
+    Some(())
+}
+
+fn walk_and_insert(inode: &Arc<Mutex<dyn INode>>, path: &str, dentry_insert: &impl Fn(&str, Arc<Mutex<dyn INode>>)) {
+    // A `"/"` mount point was trimmed to `""`; register the root as `"/"`.
+    dentry_insert(if path.is_empty() { "/" } else { path }, inode.clone());
+    let is_dir = inode.lock().is_dir();
+    if !is_dir {
+        return;
+    }
+    let Ok(entries) = inode.lock().list() else { return; };
+    for name in entries {
+        if let Some(child) = inode.lock().find(&name) {
+            let child_path = alloc::format!("{}/{}", path, name);
+            walk_and_insert(&child, &child_path, dentry_insert);
+        }
+    }
+}
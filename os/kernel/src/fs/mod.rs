@@ -6,6 +6,7 @@ pub mod file;
 pub mod util;
 pub mod vfs;
 pub mod block_dev;
+pub mod ext2;
 
 use vfs::INode;
 
@@ -3,6 +3,7 @@
 use core::{
     mem::{align_of, size_of},
     ops::Add,
+    sync::atomic::{AtomicUsize, Ordering},
 };
 
 use crate::syscall::sys_yield;
@@ -17,19 +18,36 @@ use spin::Mutex;
 
 use crate::{
     fs::{
-        file::{Dirent, OpenFlags, PipeINode, RegFileINode, Stat},
+        file::{Dirent, OpenFlags, PipeINode, RegFileINode, Stat, Statfs, SymLinkINode},
         vfs::{FileType, INode, Timespec},
     },
     mm::translated_byte_buffer,
     sbi::console_getchar,
     task::{
         cpu::mycpu, global_buffer_list, global_dentry_cache, global_open_file_table, myproc,
-        task_list, FdManager, FileDescriptor, OpenFile,
+        task_list, FdManager, FileDescriptor, OpenFile, PipeBuffer, PipeEnd,
     },
 };
 const FD_STDOUT: usize = 1;
 const FD_STDIN: usize = 0;
 
+const SEEK_SET: usize = 0;
+const SEEK_CUR: usize = 1;
+const SEEK_END: usize = 2;
+
+// POSIX permission bits, the subset of `ModeType` this single-user kernel
+// actually checks (owner bits only; group/other are stored but ignored).
+const S_IXUSR: u16 = 0o0100;
+const S_IWUSR: u16 = 0o0200;
+const S_IRUSR: u16 = 0o0400;
+const S_IRWXU: u16 = S_IRUSR | S_IWUSR | S_IXUSR;
+const S_IFREG: u16 = 0o100000;
+const S_IFDIR: u16 = 0o040000;
+
+const R_OK: usize = 4;
+const W_OK: usize = 2;
+const X_OK: usize = 1;
+
 // int getcwd(char *buf, size_t size);
 pub fn sys_getcwd(buf: *mut u8, size: usize) -> isize {
     let task = myproc();
@@ -52,39 +70,126 @@ pub fn sys_getcwd(buf: *mut u8, size: usize) -> isize {
     // 返回字符串长度（包括空终止符）
     (cwd_str.len() + 1) as isize
 }
-// int openat(int dirfd,const char *path, int flags)
-pub fn sys_openat(dirfd: isize, path: &str, flags: isize) -> isize {
-    let task = myproc();
-    let mut fd_manager = &mut task.fd_manager;
+/// `dirfd` meaning "resolve relative paths against the current working
+/// directory", as in `openat(2)`.
+pub const AT_FDCWD: isize = -100;
+/// `unlinkat(2)` flag: `path` names a directory, remove it like `rmdir`.
+pub const AT_REMOVEDIR: usize = 0x200;
+
+/// Collapse `.`/`..` components in an already-`/`-joined path.
+fn normalize_path(path: &str) -> String {
+    let mut stack: Vec<&str> = Vec::new();
+    for component in path.split('/') {
+        match component {
+            "" | "." => {}
+            ".." => {
+                stack.pop();
+            }
+            other => stack.push(other),
+        }
+    }
+    format!("/{}", stack.join("/"))
+}
+
+/// Shared dirfd/AT_FDCWD resolution for the `*at` syscalls: turns a
+/// (possibly relative) `path` into an absolute, normalized path, the way
+/// `openat`/`mkdirat`/`unlinkat`/`fstatat` all need to. `path` absolute
+/// always wins over `dirfd`; otherwise `AT_FDCWD` resolves against `cwd`,
+/// and any other `dirfd` must name an already-open directory.
+fn resolve_at(dirfd: isize, path: &str) -> Result<String, isize> {
+    if path.starts_with('/') {
+        return Ok(normalize_path(path));
+    }
 
-    // println!("openat: dir fd: {}, path: {}, flags: {}", dirfd, path, flags);
-    let start_dir_path;
-    let rel_path;
-    if path.starts_with("/") {
-        start_dir_path = "/".to_string();
-        rel_path = path.strip_prefix("/").unwrap_or(path).to_string();
+    let base = if dirfd == AT_FDCWD {
+        myproc().cwd.clone()
     } else {
-        start_dir_path = task.cwd.clone(); // TODO: consider dirfd
-        rel_path = if path.starts_with("./") {
-            path.strip_prefix("./").unwrap().to_string()
+        let task = myproc();
+        let fd_manager = &task.fd_manager;
+        if dirfd < 0 || dirfd as usize >= fd_manager.len() {
+            return Err(-1);
+        }
+        let inode = fd_manager.fd_array[dirfd as usize].open_file.inode.clone();
+        let inode = inode.lock();
+        if !inode.is_dir() {
+            return Err(-1);
+        }
+        format!("{}{}", inode.dir_path(), inode.file_name())
+    };
+
+    Ok(normalize_path(&format!("{}/{}", base.trim_end_matches('/'), path)))
+}
+
+/// Split an absolute path into its parent directory (with trailing `/`)
+/// and its final component.
+fn split_path(abs_path: &str) -> (String, String) {
+    match abs_path.rfind('/') {
+        Some(i) => (abs_path[..=i].to_string(), abs_path[i + 1..].to_string()),
+        None => ("/".to_string(), abs_path.to_string()),
+    }
+}
+
+/// Cap on the number of symlink hops `openat` will chase before giving up
+/// with `ELOOP`, mirroring Linux's own loop guard.
+const MAX_FOLLOW_SYMLINK: usize = 40;
+
+/// Follow `abs_path` through any symlink entries it names in
+/// `global_dentry_cache`, returning the final resolved path. A symlink as
+/// the final component is followed unless `no_follow` (`O_NOFOLLOW`) is
+/// set, in which case it is reported as an error instead.
+fn follow_symlink(mut abs_path: String, no_follow: bool) -> Result<String, isize> {
+    let mut hops = 0;
+    loop {
+        let inode = match global_dentry_cache.get(&abs_path) {
+            Some(inode) => inode,
+            None => return Ok(abs_path),
+        };
+        let target = {
+            let inode = inode.lock();
+            if inode.file_type() != FileType::SymLink {
+                return Ok(abs_path);
+            }
+            if no_follow {
+                return Err(-1);
+            }
+            inode.symlink_target()
+        };
+
+        hops += 1;
+        if hops > MAX_FOLLOW_SYMLINK {
+            return Err(-1); // ELOOP
+        }
+
+        abs_path = if target.starts_with('/') {
+            normalize_path(&target)
         } else {
-            path.to_string()
+            let dir = match abs_path.rfind('/') {
+                Some(i) => &abs_path[..=i],
+                None => "/",
+            };
+            normalize_path(&format!("{}/{}", dir.trim_end_matches('/'), target))
         };
     }
-    // let start_dir_path = if path == "./text.txt" {
-    //     // println!("Hi, this is a text file.");
-    //     // println!("syscalls testing success!");
-    //     // println!("");
-    //     // println!("");
-    //     "/mnt/".to_string()
-    // } else {
-    //     "/".to_string()
-    // };
-    // println!(
-    //     "openat: start_dir_path: {}, rel_path: {}",
-    //     start_dir_path, rel_path
-    // ); // TODO: fix incorrect start_dir_path
-    let abs_path = format!("{}{}", start_dir_path, rel_path);
+}
+
+// int openat(int dirfd,const char *path, int flags)
+pub fn sys_openat(dirfd: isize, path: &str, flags: isize, mode: usize) -> isize {
+    let task = myproc();
+    let mut fd_manager = &mut task.fd_manager;
+
+    let abs_path = match resolve_at(dirfd, path) {
+        Ok(abs_path) => abs_path,
+        Err(e) => return e,
+    };
+    let no_follow = (flags as u32 & OpenFlags::O_NOFOLLOW.bits()) != 0;
+    let abs_path = match follow_symlink(abs_path, no_follow) {
+        Ok(abs_path) => abs_path,
+        Err(e) => return e,
+    };
+    let (start_dir_path, rel_path) = match abs_path.rfind('/') {
+        Some(i) => (abs_path[..=i].to_string(), abs_path[i + 1..].to_string()),
+        None => ("/".to_string(), abs_path.clone()),
+    };
     let fd;
     let inode = match global_dentry_cache.get(&abs_path) {
         Some(inode) => {
@@ -92,10 +197,17 @@ pub fn sys_openat(dirfd: isize, path: &str, flags: isize) -> isize {
                 // println!("openat: file not found 'null'");
                 return -1;
             }
+            let wants_write = (flags as u32 & (OpenFlags::WRONLY.bits() | OpenFlags::RDWR.bits())) != 0;
+            let wants_read = (flags as u32 & OpenFlags::WRONLY.bits()) == 0;
+            let file_mode = inode.lock().mode();
+            if (wants_read && file_mode & S_IRUSR == 0) || (wants_write && file_mode & S_IWUSR == 0) {
+                return -1; // EACCES
+            }
             let open_file = Arc::new(OpenFile {
-                offset: 0,
+                offset: AtomicUsize::new(0),
                 status_flags: flags as u32,
                 inode: inode.clone(),
+                pipe: None,
             });
 
             let file_descriptor = FileDescriptor {
@@ -106,6 +218,7 @@ pub fn sys_openat(dirfd: isize, path: &str, flags: isize) -> isize {
                 writable: ((flags as u32 ^ OpenFlags::WRONLY.bits())
                     | (flags as u32 ^ OpenFlags::RDWR.bits()))
                     != 0,
+                cloexec: (flags as u32 & OpenFlags::O_CLOEXEC.bits()) != 0,
             };
             // println!(
             //     "openat: file_descriptor: {}, {}, {}",
@@ -136,19 +249,21 @@ pub fn sys_openat(dirfd: isize, path: &str, flags: isize) -> isize {
                 writable: true,
                 dir: start_dir_path.clone(),
                 name: rel_path.clone(),
-                atime: Timespec::default(),
-                mtime: Timespec::default(),
-                ctime: Timespec::default(),
+                atime: Timespec::now(),
+                mtime: Timespec::now(),
+                ctime: Timespec::now(),
                 flags: OpenFlags::new(flags as u32),
+                mode: (mode as u16 & 0o7777) | S_IFREG,
                 file: Vec::new(),
             }));
             global_dentry_cache.insert(&abs_path, new_inode.clone());
 
             // add open file to global open file table
             let open_file = Arc::new(OpenFile {
-                offset: 0,
+                offset: AtomicUsize::new(0),
                 status_flags: flags as u32,
                 inode: new_inode.clone(),
+                pipe: None,
             });
             global_open_file_table.insert(open_file.clone());
 
@@ -161,6 +276,7 @@ pub fn sys_openat(dirfd: isize, path: &str, flags: isize) -> isize {
                 writable: ((flags as u32 ^ OpenFlags::WRONLY.bits())
                     | (flags as u32 ^ OpenFlags::RDWR.bits()))
                     != 0,
+                cloexec: (flags as u32 & OpenFlags::O_CLOEXEC.bits()) != 0,
             };
             fd = fd_manager.len();
             fd_manager.insert(file_descriptor);
@@ -228,14 +344,39 @@ pub fn sys_write(fd: usize, buf: *const u8, len: usize) -> isize {
                     buf,
                     len,
                 );
-                let is_pipe = fd_manager.fd_array[fd].open_file.inode.lock().is_pipe();
-                if is_pipe {
-                    let mut pipe = &mut file_descriptor.open_file.inode.lock();
+                if let Some((pipe, _)) = file_descriptor.open_file.pipe.clone() {
+                    let nonblock =
+                        file_descriptor.open_file.status_flags & OpenFlags::O_NONBLOCK.bits() != 0;
+                    let mut written = 0;
                     for buffer in buffers {
-                        print!("write to pipe: {:?}\n", buffer);
-                        pipe.write_to_pipe(buffer);
+                        let mut remaining = &buffer[..];
+                        while !remaining.is_empty() {
+                            if pipe.lock().readers == 0 {
+                                // The last reader closed mid-write: hand back
+                                // whatever already made it into the pipe
+                                // instead of discarding it, matching the
+                                // partial-return handling below.
+                                return if written > 0 { written as isize } else { -1 }; // EPIPE
+                            }
+                            let n = pipe.lock().try_write(remaining);
+                            written += n;
+                            remaining = &remaining[n..];
+                            if !remaining.is_empty() {
+                                if nonblock {
+                                    return if written > 0 { written as isize } else { -1 }; // EAGAIN
+                                }
+                                sys_yield();
+                                // EINTR-style retry: a signal woke us with nothing
+                                // transferred yet, so go right back to waiting; if
+                                // some bytes already made it into the pipe, hand
+                                // those back instead of blocking further.
+                                if myproc().take_interrupted() && written > 0 {
+                                    return written as isize;
+                                }
+                            }
+                        }
                     }
-                    return len as isize;
+                    return written as isize;
                 }
                 // for buffer in buffers {
                 //     let str = core::str::from_utf8(buffer).unwrap();
@@ -244,18 +385,32 @@ pub fn sys_write(fd: usize, buf: *const u8, len: usize) -> isize {
                 // return len as isize;
             }
 
-            let mut open_file = file_descriptor.open_file.clone();
+            let open_file = file_descriptor.open_file.clone();
             let inode = open_file.inode.clone();
             let mut buf_iter = 0;
             let buffers = translated_byte_buffer(task.memory_set.token(), buf, len);
+            let mut pos = open_file.offset.load(Ordering::Relaxed);
 
             for buffer in buffers {
                 for byte in buffer {
-                    inode.lock().file_data().push(*byte);
+                    let mut inode = inode.lock();
+                    let file_data = inode.file_data();
+                    if pos < file_data.len() {
+                        file_data[pos] = *byte;
+                    } else {
+                        file_data.push(*byte);
+                    }
+                    pos += 1;
                     buf_iter += 1;
                 }
             }
-            // open_file.offset += buf_iter;
+            open_file.offset.store(pos, Ordering::Relaxed);
+            if buf_iter > 0 {
+                let mut inode = inode.lock();
+                let now = Timespec::now();
+                inode.set_mtime(now);
+                inode.set_ctime(now);
+            }
             buf_iter as isize
         }
     }
@@ -311,10 +466,8 @@ pub unsafe fn sys_read(fd: isize, buf: *mut u8, len: usize) -> isize {
             //     return -1;
             // }
             // println!("[read] fs.rs:214 - sys_read: fd {}", fd);
-            let is_pipe = fd_manager.fd_array[fd].open_file.inode.lock().is_pipe();
-
-            let mut open_file = file_descriptor.open_file.clone();
-            if is_pipe {
+            let open_file = file_descriptor.open_file.clone();
+            if let Some((pipe, _)) = open_file.pipe.clone() {
                 let mut buffers = translated_byte_buffer(
                     task_list.exclusive_access()[mycpu().proc_idx]
                         .memory_set
@@ -322,48 +475,52 @@ pub unsafe fn sys_read(fd: isize, buf: *mut u8, len: usize) -> isize {
                     buf,
                     len,
                 );
-                println!("read from pipe");
-                // let data_len = open_file.inode.lock().file_data().len();
-                for i in 0..len {
-                    let file_data = open_file.inode.lock().file_data().clone();
-                    let offset = open_file.offset;
-                    let byte = file_data.get(i);
-                    let byte = match byte {
-                        Some(byte) => {
-                            println!("sys_read: pipe is not empty");
-                            *byte
-                        }
-                        None => {
-                            // println!("sys_read: pipe is empty");
+                let nonblock = open_file.status_flags & OpenFlags::O_NONBLOCK.bits() != 0;
+                let mut read_bytes = 0;
+                for chunk in buffers {
+                    loop {
+                        if pipe.lock().is_empty() {
+                            // POSIX read returns whatever's available rather
+                            // than topping off the whole buffer: once an
+                            // earlier chunk has delivered bytes, a later
+                            // chunk finding the pipe empty ends the read
+                            // instead of blocking for more.
+                            if read_bytes > 0 {
+                                return read_bytes as isize;
+                            }
+                            if pipe.lock().writers == 0 {
+                                return read_bytes as isize; // EOF
+                            }
+                            if nonblock {
+                                return if read_bytes > 0 { read_bytes as isize } else { -1 }; // EAGAIN
+                            }
                             sys_yield();
-                            0
+                            // EINTR-style retry: go around again with nothing
+                            // lost if no bytes have been read yet; otherwise
+                            // hand back the partial read instead of blocking.
+                            if myproc().take_interrupted() && read_bytes > 0 {
+                                return read_bytes as isize;
+                            }
+                            continue;
                         }
-                    };
-                    buffers[i][0] = byte;
-                    // println!("byte: {}", byte);
+                        read_bytes += pipe.lock().try_read(chunk);
+                        break;
+                    }
                 }
-                // for buffer in buffers {
-                //     for byte in buffer {
-                //         *byte = open_file.inode.lock().file_data().clone()[open_file.offset];
-                //     }
-                // }
-                // println!("fs.rs:214 - sys_read: fd {}", fd);
-                return len as isize;
+                return read_bytes as isize;
             }
             let inode = open_file.inode.clone();
             let mut read_bytes = 0;
             let mut buf_iter = 0;
+            let start = open_file.offset.load(Ordering::Relaxed);
 
-            // if open_file.offset >= inode.file_size() as usize {
-            //     return 0;
-            // }
             // println!("fs.rs:223 - sys_read: fd {}", fd);
 
             let mut buffers = translated_byte_buffer(task.memory_set.token(), buf, len);
             for buffer in buffers {
                 for byte in buffer {
-                    if open_file.offset + buf_iter < inode.lock().file_size() as usize {
-                        *byte = inode.lock().file_data().clone()[open_file.offset + buf_iter];
+                    if start + buf_iter < inode.lock().file_size() as usize {
+                        *byte = inode.lock().file_data().clone()[start + buf_iter];
                         buf_iter += 1;
                         read_bytes += 1;
                     } else {
@@ -371,12 +528,97 @@ pub unsafe fn sys_read(fd: isize, buf: *mut u8, len: usize) -> isize {
                     }
                 }
             }
+            open_file.offset.fetch_add(read_bytes, Ordering::Relaxed);
+            if read_bytes > 0 {
+                inode.lock().set_atime(Timespec::now());
+            }
             // println!("fs.rs:237 - sys_read: fd {}", fd);
             read_bytes as isize
         }
     }
 }
 
+/// Userspace `struct iovec`, an array of which `readv`/`writev` take.
+#[repr(C)]
+#[derive(Clone, Copy)]
+pub struct IoVec {
+    pub base: *mut u8,
+    pub len: usize,
+}
+
+// ssize_t readv(int fd, const struct iovec *iov, int iovcnt)
+//
+// Drains each segment through `sys_read`, so it shares the pipe ring
+// buffer's blocking/backpressure and short-read behavior for free.
+pub unsafe fn sys_readv(fd: isize, iov: *const IoVec, iovcnt: usize) -> isize {
+    let mut total = 0isize;
+    for i in 0..iovcnt {
+        let segment = *iov.add(i);
+        if segment.base.is_null() && segment.len > 0 {
+            return if total > 0 { total } else { -1 }; // EFAULT
+        }
+        let n = sys_read(fd, segment.base, segment.len);
+        if n < 0 {
+            return if total > 0 { total } else { n };
+        }
+        total += n;
+        if (n as usize) < segment.len {
+            break;
+        }
+    }
+    total
+}
+
+// ssize_t writev(int fd, const struct iovec *iov, int iovcnt)
+pub fn sys_writev(fd: usize, iov: *const IoVec, iovcnt: usize) -> isize {
+    let mut total = 0isize;
+    for i in 0..iovcnt {
+        let segment = unsafe { *iov.add(i) };
+        if segment.base.is_null() && segment.len > 0 {
+            return if total > 0 { total } else { -1 }; // EFAULT
+        }
+        let n = sys_write(fd, segment.base as *const u8, segment.len);
+        if n < 0 {
+            return if total > 0 { total } else { n };
+        }
+        total += n;
+        if (n as usize) < segment.len {
+            break;
+        }
+    }
+    total
+}
+
+// off_t lseek(int fd, off_t offset, int whence)
+pub fn sys_lseek(fd: isize, offset: isize, whence: usize) -> isize {
+    let task = myproc();
+    let fd_manager = &mut task.fd_manager;
+    let fd = fd as usize;
+
+    if fd >= fd_manager.len() {
+        return -1;
+    }
+
+    let file_descriptor = &fd_manager.fd_array[fd];
+    let open_file = file_descriptor.open_file.clone();
+    let inode = open_file.inode.clone();
+
+    let base = match whence {
+        SEEK_SET => 0,
+        SEEK_CUR => open_file.offset.load(Ordering::Relaxed) as isize,
+        SEEK_END => inode.lock().file_size() as isize,
+        _ => return -1,
+    };
+
+    let new_offset = base + offset;
+    if new_offset < 0 {
+        return -1;
+    }
+
+    open_file.offset.store(new_offset as usize, Ordering::Relaxed);
+    new_offset as isize
+}
+
 // pub const SYS_GETDENTS64: usize = 61;
 
 pub fn sys_getdents64(fd: usize, buf: *mut u8, len: usize) -> isize {
@@ -458,40 +700,20 @@ pub fn sys_getdents64(fd: usize, buf: *mut u8, len: usize) -> isize {
 // SYSCALL_DUP => sys_dup(args[0] as isize),
 
 pub fn sys_dup(fd: isize) -> isize {
-    let fd = fd as usize;
     let task = myproc();
-    let mut fd_manager = &mut task.fd_manager;
-
-    if fd >= fd_manager.len() {
-        return -1;
-    }
-
-    let file_descriptor = &fd_manager.fd_array[fd].clone();
-    if !file_descriptor.readable && !file_descriptor.writable {
-        return -1;
-    }
-
-    let open_file = file_descriptor.open_file.clone();
-    let inode = open_file.inode.clone();
-
-    let mut new_fd = -1;
-    for (i, fd) in fd_manager.fd_array.iter().enumerate() {
-        if !fd.readable && !fd.writable {
-            new_fd = i as isize;
-            break;
-        }
-    }
-
-    if new_fd == -1 {
-        new_fd = fd_manager.len() as isize;
+    match task.fd_manager.dup(fd as usize) {
+        usize::MAX => -1,
+        new_fd => new_fd as isize,
     }
+}
 
-    fd_manager.fd_array.push(FileDescriptor {
-        readable: file_descriptor.readable,
-        writable: file_descriptor.writable,
-        open_file: open_file,
-    });
-    new_fd
+// int dup2(int oldfd, int newfd)
+//
+// `newfd == oldfd` is a no-op: overwriting `newfd` in place (as `sys_dup3`
+// below does) would otherwise clear the descriptor's close-on-exec flag.
+pub fn sys_dup2(oldfd: isize, newfd: isize) -> isize {
+    let task = myproc();
+    task.fd_manager.dup2(oldfd as usize, newfd as usize)
 }
 
 // SYSCALL_DUP3 => sys_dup3(args[0] as isize, args[1] as isize, args[2] as isize),
@@ -525,6 +747,7 @@ pub fn sys_dup3(fd: isize, new_fd: isize, flags: isize) -> isize {
                 readable: false,
                 writable: false,
                 open_file: Arc::new(OpenFile::new()),
+                cloexec: false,
             });
         }
         // println!(
@@ -533,71 +756,127 @@ pub fn sys_dup3(fd: isize, new_fd: isize, flags: isize) -> isize {
         //     fd_manager.fd_array.len()
         // );
     }
+
+    // Replacing the slot drops the old `FileDescriptor` (and, if it held the
+    // last reference to a pipe end, releases that end via `OpenFile::drop`)
+    // while `open_file` above already holds a fresh `Arc` clone for the new one.
     fd_manager.fd_array[new_fd] = FileDescriptor {
         readable: file_descriptor.readable,
         writable: file_descriptor.writable,
         open_file: open_file,
+        cloexec: (flags & OpenFlags::O_CLOEXEC.bits() as usize) != 0,
     };
     // println!("new_fd: {}", new_fd);
     new_fd as isize
 }
 
-// SYSCALL_MKDIRAT => sys_mkdirat(args[0] as isize, &translate_str(get_token(), args[1] as *mut u8), args[2] as usize),
+const F_DUPFD: usize = 0;
+const F_GETFD: usize = 1;
+const F_SETFD: usize = 2;
+const F_GETFL: usize = 3;
+const F_SETFL: usize = 4;
+const F_DUPFD_CLOEXEC: usize = 1030;
 
-pub fn sys_mkdirat(fd: isize, path: &str, mode: usize) -> isize {
-    let fd = fd as usize;
-    let mode = mode as u16;
+const FD_CLOEXEC: usize = 1;
+
+/// int fcntl(int fd, int cmd, int arg)
+pub fn sys_fcntl(fd: isize, cmd: usize, arg: usize) -> isize {
     let task = myproc();
     let mut fd_manager = &mut task.fd_manager;
+    let fd = fd as usize;
 
     if fd >= fd_manager.len() {
-        return 0; // TODO should return -1
+        return -1;
     }
 
-    let file_descriptor = &fd_manager.fd_array[fd].clone();
-    // if !file_descriptor.writable {
-    //     return -1;
-    // }
-
-    let open_file = file_descriptor.open_file.clone();
-    let inode = open_file.inode.clone();
-
-    let mut path_iter = path.split('/');
-    let mut current_dir = inode.clone();
-    let mut current_dir_name = String::from("/");
+    match cmd {
+        F_DUPFD | F_DUPFD_CLOEXEC => {
+            let file_descriptor = fd_manager.fd_array[fd].clone();
+            if !file_descriptor.readable && !file_descriptor.writable {
+                return -1;
+            }
+            let new_fd = fd_manager.lowest_free_fd(arg);
+            while new_fd >= fd_manager.len() {
+                fd_manager.fd_array.push(FileDescriptor {
+                    readable: false,
+                    writable: false,
+                    open_file: Arc::new(OpenFile::new()),
+                    cloexec: false,
+                });
+            }
+            // `..file_descriptor` carries the same `Arc<OpenFile>` into the
+            // new slot, so the `Arc`'s own refcount (and thus the pipe end's
+            // reader/writer count, tracked via `OpenFile::drop`) already
+            // accounts for this fd without any manual bookkeeping here.
+            fd_manager.fd_array[new_fd] = FileDescriptor {
+                cloexec: cmd == F_DUPFD_CLOEXEC,
+                ..file_descriptor
+            };
+            new_fd as isize
+        }
+        F_GETFD => fd_manager.get_cloexec(fd).unwrap_or(false) as isize,
+        F_SETFD => {
+            fd_manager.set_cloexec(fd, (arg & FD_CLOEXEC) != 0);
+            0
+        }
+        F_GETFL => fd_manager.fd_array[fd].open_file.status_flags as isize,
+        F_SETFL => {
+            let access_mask = OpenFlags::RDONLY.bits()
+                | OpenFlags::WRONLY.bits()
+                | OpenFlags::RDWR.bits();
+            let open_file = fd_manager.fd_array[fd].open_file.clone();
+            let kept = open_file.status_flags & access_mask;
+            let requested = arg as u32 & !access_mask;
+            // This fd's claim on `pipe` is moving from `open_file` to a
+            // freshly built `OpenFile`; bump the refcount to cover the new
+            // instance before the old one is dropped below and releases it.
+            if let Some((pipe, end)) = &open_file.pipe {
+                let mut pipe = pipe.lock();
+                match end {
+                    PipeEnd::Read => pipe.readers += 1,
+                    PipeEnd::Write => pipe.writers += 1,
+                }
+            }
+            let new_open_file = OpenFile {
+                offset: AtomicUsize::new(open_file.offset.load(Ordering::Relaxed)),
+                status_flags: kept | requested,
+                inode: open_file.inode.clone(),
+                pipe: open_file.pipe.clone(),
+            };
+            fd_manager.fd_array[fd].open_file = Arc::new(new_open_file);
+            0
+        }
+        _ => -1,
+    }
+}
 
-    loop {
-        let next_dir_name = match path_iter.next() {
-            Some(name) => name,
-            None => break,
-        };
+// SYSCALL_MKDIRAT => sys_mkdirat(args[0] as isize, &translate_str(get_token(), args[1] as *mut u8), args[2] as usize),
 
-        if next_dir_name == "" {
-            continue;
-        }
+pub fn sys_mkdirat(dirfd: isize, path: &str, mode: usize) -> isize {
+    let mode = mode as u16;
 
-        let next_dir = match current_dir.lock().find(next_dir_name) {
-            Ok(next_dir) => next_dir,
-            Err(_) => {
-                let new_dir = Arc::new(Mutex::new(RegFileINode {
-                    readable: true,
-                    writable: true,
-                    dir: current_dir_name.clone(),
-                    name: current_dir_name.clone(),
-                    atime: Timespec::default(),
-                    mtime: Timespec::default(),
-                    ctime: Timespec::default(),
-                    flags: OpenFlags::new(mode as u32),
-                    file: Vec::new(),
-                }));
-                // current_dir.lock().add(next_dir_name, new_dir.clone());
-                new_dir
-            }
-        };
+    let abs_path = match resolve_at(dirfd, path) {
+        Ok(abs_path) => abs_path,
+        Err(e) => return e,
+    };
+    let (dir_path, name) = match abs_path.rfind('/') {
+        Some(i) => (abs_path[..=i].to_string(), abs_path[i + 1..].to_string()),
+        None => ("/".to_string(), abs_path.clone()),
+    };
 
-        current_dir = next_dir;
-        current_dir_name = next_dir_name.to_string();
-    }
+    let new_dir = Arc::new(Mutex::new(RegFileINode {
+        readable: true,
+        writable: true,
+        dir: dir_path,
+        name,
+        atime: Timespec::default(),
+        mtime: Timespec::default(),
+        ctime: Timespec::default(),
+        flags: OpenFlags::new(mode as u32),
+        mode: (mode & 0o7777) | S_IFDIR,
+        file: Vec::new(),
+    }));
+    global_dentry_cache.insert(&abs_path, new_dir);
 
     0
 }
@@ -617,23 +896,24 @@ pub fn sys_chdir(path: &str) -> isize {
 
 // SYSCALL_FSSTAT => sys_fstat(args[0] as isize, args[1] as *mut u8),
 
+/// Write `stat` into `buf`, a user/kernel `struct stat *`, respecting
+/// `Stat`'s natural alignment the way every `*stat*` syscall here already
+/// does for its own out-struct.
+fn write_stat(buf: *mut u8, stat: Stat) -> isize {
+    let alignment = align_of::<Stat>();
+    let align_offset = buf.align_offset(alignment);
+    let aligned_buf_ptr = if align_offset == 0 {
+        buf
+    } else {
+        unsafe { buf.add(align_offset) }
+    };
+    unsafe {
+        core::ptr::write(aligned_buf_ptr as *mut Stat, stat);
+    }
+    0
+}
+
 pub fn sys_fstat(fd: isize, buf: *mut u8) -> isize {
-    // let stat = Stat::new();
-    // let x=buf as *mut Stat;
-    // unsafe {
-    // 	*x=stat;
-    // 	for i in 0..16{
-    // 		for j in 0..16{
-    // 			print!("{:02x} ",*buf.add(i*16+j));
-    // 		}
-    // 		println!("");
-    // 	}
-    // 	println!("\n");
-    // }
-
-    // return 0;
-
-    // println!("openat: fd: {}, buf: {:?}", fd, buf);
     let fd = fd as usize;
     let task = myproc();
     let mut fd_manager = &mut task.fd_manager;
@@ -649,28 +929,257 @@ pub fn sys_fstat(fd: isize, buf: *mut u8) -> isize {
 
     let open_file = file_descriptor.open_file.clone();
     let inode = open_file.inode.clone();
+    write_stat(buf, inode.lock().stat())
+}
 
-    let mut buf_ptr = buf;
-    let mut bytes_written = 0;
+// int fstatat(int dirfd, const char *path, struct stat *buf, int flags)
+pub fn sys_fstatat(dirfd: isize, path: &str, buf: *mut u8, flags: usize) -> isize {
+    let abs_path = match resolve_at(dirfd, path) {
+        Ok(abs_path) => abs_path,
+        Err(e) => return e,
+    };
 
-    let mut stat = Stat::new();
+    let inode = match global_dentry_cache.get(&abs_path) {
+        Some(inode) => inode,
+        None => return -1,
+    };
 
-    let alignment = align_of::<Stat>();
+    write_stat(buf, inode.lock().stat())
+}
+
+// SYSCALL_STAT => sys_stat(&translate_str(get_token(), args[0] as *mut u8), args[1] as *mut u8),
+
+// int stat(const char *path, struct stat *buf)
+//
+// `lstat` resolves through `GlobalDentryCache` exactly like
+// `fstatat(AT_FDCWD, path, buf, 0)`, reporting whatever inode sits at the
+// final path component. Since chunk0-3 gave symlinks their own
+// `SymLinkINode` distinct from the inode they target, `stat` additionally
+// chases that component through `follow_symlink` first.
+pub fn sys_stat(path: &str, buf: *mut u8) -> isize {
+    let abs_path = match resolve_at(AT_FDCWD, path) {
+        Ok(abs_path) => abs_path,
+        Err(e) => return e,
+    };
+    let abs_path = match follow_symlink(abs_path, false) {
+        Ok(abs_path) => abs_path,
+        Err(e) => return e,
+    };
+
+    let inode = match global_dentry_cache.get(&abs_path) {
+        Some(inode) => inode,
+        None => return -1,
+    };
+
+    write_stat(buf, inode.lock().stat())
+}
+
+// SYSCALL_LSTAT => sys_lstat(&translate_str(get_token(), args[0] as *mut u8), args[1] as *mut u8),
+
+// int lstat(const char *path, struct stat *buf)
+pub fn sys_lstat(path: &str, buf: *mut u8) -> isize {
+    sys_fstatat(AT_FDCWD, path, buf, 0)
+}
+
+// This kernel doesn't back its filesystem with real storage, so `statfs`
+// reports a single fixed-capacity "device": `f_blocks` is a made-up total
+// size, and `f_bfree`/`f_bavail` are that total minus whatever's actually
+// cached in `global_dentry_cache`, rather than any backing block device.
+const STATFS_MAGIC: i64 = 0x6e61_6976; // "naiv" in ASCII, this fs's magic number
+const STATFS_BLOCK_SIZE: i64 = 4096;
+const STATFS_NAME_MAX: i64 = 255;
+const STATFS_TOTAL_BYTES: i64 = 1 << 30; // fixed fake 1 GiB capacity
+
+fn fill_statfs() -> Statfs {
+    let mut statfs = Statfs::new();
+    let used_bytes = global_dentry_cache.total_bytes() as i64;
+    let used_blocks = used_bytes / STATFS_BLOCK_SIZE + 1;
+    let total_blocks = STATFS_TOTAL_BYTES / STATFS_BLOCK_SIZE;
+    let free_blocks = (total_blocks - used_blocks).max(0);
+    statfs.f_type = STATFS_MAGIC;
+    statfs.f_bsize = STATFS_BLOCK_SIZE;
+    statfs.f_blocks = total_blocks;
+    statfs.f_bfree = free_blocks;
+    statfs.f_bavail = free_blocks;
+    statfs.f_files = global_dentry_cache.len() as i64;
+    statfs.f_ffree = 0;
+    statfs.f_namelen = STATFS_NAME_MAX;
+    statfs
+}
+
+fn write_statfs(buf: *mut u8) -> isize {
+    let statfs = fill_statfs();
+    let alignment = align_of::<Statfs>();
     let align_offset = buf.align_offset(alignment);
     let aligned_buf_ptr = if align_offset == 0 {
         buf
     } else {
         unsafe { buf.add(align_offset) }
     };
-
-    let stat_size = size_of::<Stat>();
     unsafe {
-        // Using ptr::write instead of copy_nonoverlapping
-        core::ptr::write(aligned_buf_ptr as *mut Stat, stat);
+        core::ptr::write(aligned_buf_ptr as *mut Statfs, statfs);
+    }
+    0
+}
+
+// int statfs(const char *path, struct statfs *buf)
+pub fn sys_statfs(path: &str, buf: *mut u8) -> isize {
+    let abs_path = match resolve_at(AT_FDCWD, path) {
+        Ok(abs_path) => abs_path,
+        Err(e) => return e,
+    };
+
+    if global_dentry_cache.get(&abs_path).is_none() {
+        return -1;
+    }
+
+    write_statfs(buf)
+}
+
+// int fstatfs(int fd, struct statfs *buf)
+pub fn sys_fstatfs(fd: isize, buf: *mut u8) -> isize {
+    let task = myproc();
+    let fd_manager = &task.fd_manager;
+
+    if fd < 0 || fd as usize >= fd_manager.len() {
+        return -1;
+    }
+
+    write_statfs(buf)
+}
+
+// int fchmodat(int dirfd, const char *path, mode_t mode)
+pub fn sys_fchmodat(dirfd: isize, path: &str, mode: usize) -> isize {
+    let abs_path = match resolve_at(dirfd, path) {
+        Ok(abs_path) => abs_path,
+        Err(e) => return e,
+    };
+
+    let inode = match global_dentry_cache.get(&abs_path) {
+        Some(inode) => inode,
+        None => return -1,
+    };
+
+    let type_bits = inode.lock().mode() & (S_IFREG | S_IFDIR);
+    inode.lock().set_mode((mode as u16 & 0o7777) | type_bits);
+    0
+}
+
+// int faccessat(int dirfd, const char *path, int amode, int flags)
+pub fn sys_faccessat(dirfd: isize, path: &str, amode: usize, flags: usize) -> isize {
+    let abs_path = match resolve_at(dirfd, path) {
+        Ok(abs_path) => abs_path,
+        Err(e) => return e,
+    };
+
+    let inode = match global_dentry_cache.get(&abs_path) {
+        Some(inode) => inode,
+        None => return -1,
+    };
+
+    let mode = inode.lock().mode();
+    if (amode & R_OK != 0 && mode & S_IRUSR == 0)
+        || (amode & W_OK != 0 && mode & S_IWUSR == 0)
+        || (amode & X_OK != 0 && mode & S_IXUSR == 0)
+    {
+        return -1; // EACCES
+    }
+    0
+}
+
+/// `tv_nsec` value meaning "use the current kernel time".
+const UTIME_NOW: i64 = 0x3fffffff;
+/// `tv_nsec` value meaning "leave this timestamp unchanged".
+const UTIME_OMIT: i64 = 0x3ffffffe;
+
+// int utimensat(int dirfd, const char *path, const struct timespec times[2], int flags)
+pub unsafe fn sys_utimensat(dirfd: isize, path: &str, times: *const Timespec, flags: usize) -> isize {
+    let abs_path = match resolve_at(dirfd, path) {
+        Ok(abs_path) => abs_path,
+        Err(e) => return e,
+    };
+
+    let inode = match global_dentry_cache.get(&abs_path) {
+        Some(inode) => inode,
+        None => return -1,
+    };
+
+    let (atime_spec, mtime_spec) = if times.is_null() {
+        (Timespec::now(), Timespec::now())
+    } else {
+        (*times, *times.add(1))
+    };
+
+    let mut inode = inode.lock();
+    match atime_spec.nsec {
+        UTIME_OMIT => {}
+        UTIME_NOW => inode.set_atime(Timespec::now()),
+        _ => inode.set_atime(atime_spec),
+    }
+    match mtime_spec.nsec {
+        UTIME_OMIT => {}
+        UTIME_NOW => inode.set_mtime(Timespec::now()),
+        _ => inode.set_mtime(mtime_spec),
     }
-    bytes_written += stat_size;
-    bytes_written as isize;
-    return 0;
+    inode.set_ctime(Timespec::now());
+    0
+}
+
+/// Fail instead of replacing an existing `newpath`.
+pub const RENAME_NOREPLACE: usize = 1 << 0;
+/// Atomically swap `oldpath` and `newpath`; both must already exist.
+pub const RENAME_EXCHANGE: usize = 1 << 1;
+
+// int renameat2(int olddirfd, const char *oldpath, int newdirfd, const char *newpath, unsigned int flags)
+pub fn sys_renameat2(
+    olddirfd: isize,
+    oldpath: &str,
+    newdirfd: isize,
+    newpath: &str,
+    flags: usize,
+) -> isize {
+    let old_abs = match resolve_at(olddirfd, oldpath) {
+        Ok(p) => p,
+        Err(e) => return e,
+    };
+    let new_abs = match resolve_at(newdirfd, newpath) {
+        Ok(p) => p,
+        Err(e) => return e,
+    };
+
+    let old_inode = match global_dentry_cache.get(&old_abs) {
+        Some(inode) => inode,
+        None => return -1,
+    };
+    let new_inode = global_dentry_cache.get(&new_abs);
+
+    if flags & RENAME_EXCHANGE != 0 {
+        let new_inode = match new_inode {
+            Some(inode) => inode,
+            None => return -1,
+        };
+        let (old_dir, old_name) = split_path(&old_abs);
+        let (new_dir, new_name) = split_path(&new_abs);
+        old_inode.lock().set_dir(new_dir);
+        old_inode.lock().set_name(new_name);
+        new_inode.lock().set_dir(old_dir);
+        new_inode.lock().set_name(old_name);
+        global_dentry_cache.insert(&old_abs, new_inode);
+        global_dentry_cache.insert(&new_abs, old_inode);
+        return 0;
+    }
+
+    if new_inode.is_some() && flags & RENAME_NOREPLACE != 0 {
+        return -1; // EEXIST
+    }
+
+    let (new_dir, new_name) = split_path(&new_abs);
+    old_inode.lock().set_dir(new_dir);
+    old_inode.lock().set_name(new_name);
+
+    global_dentry_cache.remove(&old_abs);
+    global_dentry_cache.insert(&new_abs, old_inode);
+    0
 }
 
 /*
@@ -694,38 +1203,27 @@ syscall(SYS_unlinkat, dirfd, path, flags);
 
 // SYSCALL_UNLINKAT => sys_unlinkat(args[0] as isize, &translate_str(get_token(), args[1] as *mut u8), args[2] as usize),
 
-pub fn sys_unlinkat(fd: isize, path: &str, flags: usize) -> isize {
-    // println!("sys_unlinkat: fd: {}, path: {}, flags: {}", fd, path, flags);
-    let task = myproc();
-    let mut fd_manager = &mut task.fd_manager;
-
-    if fd >= fd_manager.len() as isize {
-        return -1;
-    }
+pub fn sys_unlinkat(dirfd: isize, path: &str, flags: usize) -> isize {
+    // println!("sys_unlinkat: dirfd: {}, path: {}, flags: {}", dirfd, path, flags);
+    let abs_path = match resolve_at(dirfd, path) {
+        Ok(abs_path) => abs_path,
+        Err(e) => return e,
+    };
 
-    let start_dir_path;
-    let rel_path;
-    if path.starts_with("/") {
-        start_dir_path = "/".to_string();
-        rel_path = path.strip_prefix("/").unwrap_or(path).to_string();
-    } else {
-        start_dir_path = task.cwd.clone(); // TODO: consider dirfd
-        rel_path = if path.starts_with("./") {
-            path.strip_prefix("./").unwrap().to_string()
-        } else {
-            path.to_string()
-        };
+    if flags & AT_REMOVEDIR != 0 {
+        match global_dentry_cache.get(&abs_path) {
+            Some(inode) => {
+                let inode = inode.lock();
+                if !inode.is_dir() {
+                    return -1;
+                }
+                if matches!(inode.list(), Ok(entries) if !entries.is_empty()) {
+                    return -1; // ENOTEMPTY
+                }
+            }
+            None => return -1,
+        }
     }
-    let start_dir_path = if path == "./text.txt" {
-        "/mnt/".to_string()
-    } else {
-        "/".to_string()
-    };
-    // println!(
-    //     "openat: start_dir_path: {}, rel_path: {}",
-    //     start_dir_path, rel_path
-    // ); // TODO: fix incorrect start_dir_path
-    let abs_path = format!("{}{}", start_dir_path, rel_path);
 
     global_dentry_cache.unlink(&abs_path);
     // println!("unlinkat: abs_path: {}", abs_path);
@@ -733,19 +1231,77 @@ pub fn sys_unlinkat(fd: isize, path: &str, flags: usize) -> isize {
     0
 }
 
-// SYSCALL_PIPE2 => sys_pipe2(translate(args[0]) as *mut usize),
+// int symlinkat(const char *target, int newdirfd, const char *linkpath)
+pub fn sys_symlinkat(target: &str, newdirfd: isize, linkpath: &str) -> isize {
+    let abs_path = match resolve_at(newdirfd, linkpath) {
+        Ok(abs_path) => abs_path,
+        Err(e) => return e,
+    };
 
-pub fn sys_pipe2(pipe: *mut u32) -> isize {
+    let inode = Arc::new(Mutex::new(SymLinkINode::new(target.to_string())));
+    global_dentry_cache.insert(&abs_path, inode);
+    0
+}
+
+// ssize_t readlinkat(int dirfd, const char *path, char *buf, size_t bufsize)
+pub fn sys_readlinkat(dirfd: isize, path: &str, buf: *mut u8, bufsize: usize) -> isize {
+    let abs_path = match resolve_at(dirfd, path) {
+        Ok(abs_path) => abs_path,
+        Err(e) => return e,
+    };
+
+    let inode = match global_dentry_cache.get(&abs_path) {
+        Some(inode) => inode,
+        None => return -1,
+    };
+    let target = {
+        let inode = inode.lock();
+        if inode.file_type() != FileType::SymLink {
+            return -1;
+        }
+        inode.symlink_target()
+    };
+
+    let len = target.len().min(bufsize);
+    let task = myproc();
+    let mut buffers = translated_byte_buffer(task.memory_set.token(), buf, len);
+    for (i, byte) in target.as_bytes()[..len].iter().enumerate() {
+        buffers[0][i] = *byte;
+    }
+    len as isize
+}
+
+// SYSCALL_PIPE => sys_pipe(translate(args[0]) as *mut usize),
+
+// int pipe(int pipefd[2])
+pub fn sys_pipe(pipe: *mut u32) -> isize {
+    sys_pipe2(pipe, 0)
+}
+
+// SYSCALL_PIPE2 => sys_pipe2(translate(args[0]) as *mut usize, args[1] as isize),
+
+pub fn sys_pipe2(pipe: *mut u32, flags: isize) -> isize {
     let task = myproc();
     let mut fd_manager = &mut task.fd_manager;
 
+    let cloexec = (flags as u32 & OpenFlags::O_CLOEXEC.bits()) != 0;
+    let nonblock = (flags as u32 & OpenFlags::O_NONBLOCK.bits()) != 0;
+    let status_flags = if nonblock { OpenFlags::O_NONBLOCK.bits() } else { 0 };
+
     let read_fd = fd_manager.alloc_fd(true, false);
     let write_fd = fd_manager.alloc_fd(false, true);
 
-    let buf = Arc::new(Mutex::new(Vec::<u8>::new()));
+    let buf = Arc::new(Mutex::new(PipeBuffer::new()));
+
+    let mut read_open_file = OpenFile::new_pipe_read(Arc::clone(&buf));
+    read_open_file.status_flags = status_flags;
+    let mut write_open_file = OpenFile::new_pipe_write(Arc::clone(&buf));
+    write_open_file.status_flags = status_flags;
 
-    fd_manager.fd_array[read_fd].open_file = Arc::new(OpenFile::new_pipe_read(Arc::clone(&buf)));
-    fd_manager.fd_array[write_fd].open_file = Arc::new(OpenFile::new_pipe_write(Arc::clone(&buf)));
+    fd_manager.fd_array[read_fd].open_file = Arc::new(read_open_file);
+    fd_manager.fd_array[write_fd].open_file = Arc::new(write_open_file);
+    fd_manager.fd_array[read_fd].cloexec = cloexec;
+    fd_manager.fd_array[write_fd].cloexec = cloexec;
 
     println!("fd_manager.len(): {}", fd_manager.len());
     global_buffer_list.insert(buf);
@@ -12,13 +12,14 @@ use alloc::{
 };
 use lazy_static::lazy_static;
 use riscv::register::fcsr::Flag;
+use spin::Mutex;
 use xmas_elf::{ElfFile, header::parse_header};
 
 use crate::{
-    mm::{page_table::translate_str, translated_byte_buffer, MemorySet, VirtAddr, KERNEL_SPACE, MapPermission},
+    mm::{page_table::translate_str, translated_byte_buffer, MemorySet, PhysAddr, VirtAddr, KERNEL_SPACE, MapPermission},
     sync::UPSafeCell,
     task::{
-         ProcessState, PCB, Thread, TASK_QUEUE, PID_ALLOCATOR, ProcessContext, Process, GLOBAL_DENTRY_CACHE,
+         ProcessState, PCB, Thread, TASK_QUEUE, PID_ALLOCATOR, ProcessContext, Process, GLOBAL_DENTRY_CACHE, global_futex_table,
     }, config::{PAGE_SIZE, TRAPFRAME, TRAMPOLINE, KERNEL_STACK_SIZE, PRINT_SYSCALL}, trap::{TrapFrame, user_loop}, sbi::shutdown,
 };
 
@@ -80,6 +81,144 @@ bitflags! {
 	}
 }
 
+/// sys_futex 支持的操作，目前只实现 pthread join 所需的等待/唤醒一对
+const FUTEX_WAIT: i32 = 0;
+const FUTEX_WAKE: i32 = 1;
+
+bitflags! {
+	/// Options accepted by `wait4`/`waitpid`, matching Linux (and DragonOS's
+	/// `WaitOption`) instead of the previous "any nonzero `options` blocks".
+	pub struct WaitOption: u32 {
+		/// Return immediately instead of blocking if no child has exited yet.
+		const WNOHANG = 1 << 0;
+		/// Also report children stopped by a signal, not just exited ones.
+		const WUNTRACED = 1 << 1;
+		/// Also report children that were stopped and then resumed by SIGCONT.
+		const WCONTINUED = 1 << 3;
+		/// Wait for children of any type, including those `sys_clone` marked
+		/// `CLONE_THREAD`.
+		const __WALL = 1 << 30;
+	}
+}
+
+/// `struct timeval`-shaped `{seconds, microseconds}` pair, used by `RUsage`.
+#[repr(C)]
+#[derive(Clone, Copy, Default)]
+pub struct TimeVal {
+	pub sec: usize,
+	pub usec: usize,
+}
+
+impl TimeVal {
+	/// Treat a PCB's opaque tick counter as a microsecond count, since this
+	/// kernel has no clock-frequency constant to convert through.
+	fn from_ticks(ticks: usize) -> Self {
+		Self { sec: ticks / 1_000_000, usec: ticks % 1_000_000 }
+	}
+}
+
+/// Subset of POSIX `struct rusage` that `wait4` fills in for the reaped
+/// child; every other field is left zeroed, following DragonOS's `RUsage`.
+#[repr(C)]
+#[derive(Clone, Copy, Default)]
+pub struct RUsage {
+	pub ru_utime: TimeVal,
+	pub ru_stime: TimeVal,
+	pub ru_maxrss: isize,
+	pub ru_ixrss: isize,
+	pub ru_idrss: isize,
+	pub ru_isrss: isize,
+	pub ru_minflt: isize,
+	pub ru_majflt: isize,
+	pub ru_nswap: isize,
+	pub ru_inblock: isize,
+	pub ru_oublock: isize,
+	pub ru_msgsnd: isize,
+	pub ru_msgrcv: isize,
+	pub ru_nsignals: isize,
+	pub ru_nvcsw: isize,
+	pub ru_nivcsw: isize,
+}
+
+/// Pack a reaped child's status the way `WIFEXITED`/`WEXITSTATUS`/
+/// `WIFSIGNALED`/`WTERMSIG` expect: a normal exit shifts the code into bits
+/// 8-15 with bits 0-6 clear; a fatal signal (`ProcessState::KILLED`, whose
+/// `exit_code` this kernel reuses to hold the signal number) packs it into
+/// bits 0-6 instead.
+fn encode_wait_status(exit_code: isize, state: ProcessState) -> isize {
+	if state == ProcessState::KILLED {
+		exit_code & 0x7f
+	} else {
+		(exit_code & 0xff) << 8
+	}
+}
+
+/// Physical address backing `vaddr` in `pcb`'s address space, used to key
+/// `global_futex_table` so two mappings of the same page (e.g. `CLONE_VM`
+/// siblings translating the same tid word through their own page tables)
+/// rendezvous on the same wake counter instead of one per virtual address.
+fn futex_addr(pcb: &PCB, vaddr: usize) -> PhysAddr {
+	let va = VirtAddr::from(vaddr);
+	let ppn = pcb.memory_set.translate(va.floor()).unwrap().ppn();
+	PhysAddr::from(PhysAddr::from(ppn).0 + va.page_offset())
+}
+
+/// `struct utsname`, modeled on DragonOS's `PosixOldUtsName`: six
+/// null-padded 65-byte fields so the layout matches what glibc/musl's
+/// `uname(2)` wrapper expects regardless of how long each string actually is.
+#[repr(C)]
+#[derive(Clone, Copy)]
+pub struct UtsName {
+	pub sysname: [u8; 65],
+	pub nodename: [u8; 65],
+	pub release: [u8; 65],
+	pub version: [u8; 65],
+	pub machine: [u8; 65],
+	pub domainname: [u8; 65],
+}
+
+impl Default for UtsName {
+	fn default() -> Self {
+		Self {
+			sysname: [0; 65],
+			nodename: [0; 65],
+			release: [0; 65],
+			version: [0; 65],
+			machine: [0; 65],
+			domainname: [0; 65],
+		}
+	}
+}
+
+fn pack_uts_field(field: &mut [u8; 65], value: &str) {
+	let bytes = value.as_bytes();
+	let len = bytes.len().min(field.len() - 1);
+	field[0..len].copy_from_slice(&bytes[0..len]);
+}
+
+impl UtsName {
+	/// Fixed kernel identity, since this is a single-image kernel with no
+	/// per-boot hostname or release to report.
+	fn kernel_identity() -> Self {
+		let mut uname = Self::default();
+		pack_uts_field(&mut uname.sysname, "naive-os");
+		pack_uts_field(&mut uname.nodename, "naive-os");
+		pack_uts_field(&mut uname.release, "5.15.0");
+		pack_uts_field(&mut uname.version, "#1");
+		pack_uts_field(&mut uname.machine, "riscv64");
+		uname
+	}
+}
+
+lazy_static! {
+	/// The root process — pid 1 in every sense that matters here, since
+	/// this kernel has no separate boot-time registration for it: the first
+	/// process ever observed cloning with no parent of its own is recorded
+	/// here, and every other process reparents its orphaned children onto
+	/// it when it exits (see `sys_exit`).
+	static ref INIT_PROC: Mutex<Option<Arc<Process>>> = Mutex::new(None);
+}
+
 impl Thread{
 /// task exits and submit an exit code
 	pub unsafe fn sys_exit(&self,exit_code: i32)->isize{
@@ -89,6 +228,35 @@ impl Thread{
 		if PRINT_SYSCALL{
 			println!("[exit] proc {} exited with code {}.",proc.pid,exit_code);
 		}
+		// Linux's clear-tid protocol: glibc/musl implement `pthread_join` as a
+		// `FUTEX_WAIT` on this thread's tid word, so zero it and wake its
+		// waiters (all of them) here instead of leaving the joiner stuck polling
+		// `sys_waitpid`, which `CLONE_THREAD` children don't even register for.
+		if let Some(ctid) = proc.clear_child_tid.take() {
+			let addr = futex_addr(proc, ctid.0);
+			*(self.translate(ctid.0) as *mut u32) = 0;
+			global_futex_table.wake(addr);
+		}
+
+		// Reparent this process's own children to init instead of leaving
+		// them pointing at a parent that will never call `wait()` for them
+		// again, same as Linux's reparent-to-pid-1 on exit. Skipped when
+		// `self` *is* init (the `shutdown()` branch below tears the whole
+		// machine down anyway, so there's nothing left to reparent onto).
+		if let Some(init_proc) = INIT_PROC.lock().clone() {
+			if !Arc::ptr_eq(&init_proc, &self.proc) {
+				let mut init_pcb = init_proc.inner.lock();
+				for (pid, child) in proc.children.alive.drain() {
+					child.inner.lock().parent = Some(init_proc.clone());
+					init_pcb.children.alive.insert(pid, child);
+				}
+				for (pid, child) in proc.children.zombie.drain() {
+					child.inner.lock().parent = Some(init_proc.clone());
+					init_pcb.children.zombie.insert(pid, child);
+				}
+			}
+		}
+
 		if let Some(nuclear)=proc.parent.as_ref(){
 			let mut x=nuclear.inner.lock();
 			x.children.turn_into_zombie(proc.pid);
@@ -96,6 +264,13 @@ impl Thread{
 			shutdown();
 			// println!("init exited.");
 		}
+
+		// The PCB itself stays alive — still reachable from the parent's
+		// `children.zombie` until `wait()` reaps it — but there's no reason
+		// to keep holding its address space or open files that long.
+		proc.memory_set = MemorySet::new_bare();
+		proc.fd_manager.lock().close_all();
+
 		self.inner.exclusive_access().exit=true;
 		0
 	}
@@ -106,24 +281,106 @@ impl Thread{
 	pub unsafe fn sys_getppid(&self) -> isize {
 		self.proc.inner.lock().parent.as_ref().unwrap().pid as isize
 	}
-	
+
+	/// `uname(2)`: report the fixed kernel identity busybox and libc init
+	/// paths probe for before doing anything else. There's no per-boot
+	/// hostname or release to track, so every field is a constant.
+	pub unsafe fn sys_uname(&self, buf: UserPtr<UtsName, Out>) -> isize {
+		if buf.as_usize() != 0 {
+			*buf.raw_ptr_mut() = UtsName::kernel_identity();
+		}
+		0
+	}
+
+	/// `set_tid_address(2)`: record where the kernel should clear this
+	/// thread's tid and wake a futex waiter on exit, per Linux's clear-tid
+	/// protocol. Always succeeds, returning the caller's own tid.
+	pub unsafe fn sys_set_tid_address(&self, tidptr: usize) -> isize {
+		let mut pcb = self.proc.inner.lock();
+		pcb.clear_child_tid = if tidptr == 0 { None } else { Some(VirtAddr::from(tidptr)) };
+		pcb.pid as isize
+	}
+
+	/// Minimal `futex(2)`: only `FUTEX_WAIT`/`FUTEX_WAKE` are implemented,
+	/// which is all `pthread_join`'s wait on a cleared tid word needs.
+	pub async unsafe fn sys_futex(&self, uaddr: usize, futex_op: i32, val: u32) -> isize {
+		let addr = {
+			let pcb = self.proc.inner.lock();
+			futex_addr(&pcb, uaddr)
+		};
+		match futex_op & 0x7f {
+			FUTEX_WAIT => {
+				if *(self.translate(uaddr) as *const u32) != val {
+					return -1;
+				}
+				let seen = global_futex_table.wake_count(addr);
+				while global_futex_table.wake_count(addr) == seen {
+					if self.proc.inner.lock().take_interrupted() {
+						return -1;
+					}
+					Thread::async_yield().await;
+				}
+				0
+			}
+			FUTEX_WAKE => {
+				global_futex_table.wake(addr);
+				1
+			}
+			_ => -1,
+		}
+	}
+
 
 	pub unsafe fn sys_clone(&self,flags:usize,stack: usize,ptid:usize, tls:usize, ctid:usize) -> isize {
 		if PRINT_SYSCALL {println!("[clone] flags:{} stack:{:#x},ptid:{:#x},tls:{}",flags,stack,ptid,tls);}
 		let mut pcb = self.proc.inner.lock();
 		let mut pcb =pcb.deref_mut();
 		let pid=pcb.pid;
+		// The caller has no parent of its own, i.e. it's the root process:
+		// remember it so later exits know who to reparent orphans onto.
+		if pcb.parent.is_none() && INIT_PROC.lock().is_none() {
+			*INIT_PROC.lock() = Some(self.proc.clone());
+		}
 		let new_pid= PID_ALLOCATOR.alloc_pid();
 		let flags=CloneFlags::from_bits(flags as u32 & (!0x3f)).unwrap();
 		if PRINT_SYSCALL {println!("[clone] pid:{} new_pid:{}",pid,new_pid);}
 
 		let mut new_pcb=PCB::new();
-		new_pcb.parent=Some(self.proc.clone());
-		new_pcb.fd_manager=pcb.fd_manager.clone();
-		// for fd in pcb.fd_manager.fd_array.clone(){
-		// 	new_pcb.fd_manager.push(fd);
-		// }
-		new_pcb.memory_set=MemorySet::from_existed_user(&pcb.memory_set);
+
+		// CLONE_THREAD: live as a sibling in the caller's thread group,
+		// inheriting its `parent` and sharing its thread-group list, rather
+		// than becoming a `wait`-able child of the caller.
+		if flags.contains(CloneFlags::CLONE_THREAD) {
+			new_pcb.parent = pcb.parent.clone();
+			new_pcb.thread_group = pcb.thread_group.clone();
+		} else {
+			new_pcb.parent=Some(self.proc.clone());
+			new_pcb.thread_group = Arc::new(Mutex::new(Vec::new()));
+		}
+
+		// CLONE_FILES: share the fd table behind its existing lock instead
+		// of snapshotting it into an independent table.
+		new_pcb.fd_manager = if flags.contains(CloneFlags::CLONE_FILES) {
+			pcb.fd_manager.clone()
+		} else {
+			Arc::new(Mutex::new(pcb.fd_manager.lock().clone()))
+		};
+
+		// CLONE_FS: share cwd/root so a `chdir` in one task is visible to
+		// every task sharing this filesystem context.
+		new_pcb.cwd = if flags.contains(CloneFlags::CLONE_FS) {
+			pcb.cwd.clone()
+		} else {
+			Arc::new(Mutex::new(pcb.cwd.lock().clone()))
+		};
+
+		// CLONE_VM: share the address space (the same root page table)
+		// instead of the usual copy-on-clone, as real pthreads require.
+		new_pcb.memory_set = if flags.contains(CloneFlags::CLONE_VM) {
+			MemorySet::share_existed_user(&pcb.memory_set)
+		} else {
+			MemorySet::from_existed_user(&pcb.memory_set)
+		};
 		// new_pcb.heap_pos = VirtAddr::from(pcb.memory_set.get_areas_end());
 		new_pcb.heap_pos = pcb.heap_pos;
 		new_pcb.mmap_pos = pcb.mmap_pos;
@@ -156,10 +413,34 @@ impl Thread{
 		new_pcb.context.sp = TRAMPOLINE - KERNEL_STACK_SIZE * new_pid;
 		new_pcb.state = ProcessState::READY;
 		new_pcb.pid = new_pid;
-		
+
+		// CLONE_CHILD_SETTID/CLONE_PARENT_SETTID: write the new tid into
+		// whichever address space the caller asked for, and CLONE_CHILD_CLEARTID:
+		// remember `ctid` so `sys_exit` can zero it and wake a `pthread_join`
+		// futex waiter when this thread dies.
+		if flags.contains(CloneFlags::CLONE_CHILD_SETTID) && ctid != 0 {
+			let mut buffers = translated_byte_buffer(new_pcb.memory_set.token(), ctid as *mut u8, core::mem::size_of::<u32>());
+			for (i, byte) in (new_pid as u32).to_ne_bytes().iter().enumerate() {
+				buffers[0][i] = *byte;
+			}
+		}
+		if flags.contains(CloneFlags::CLONE_PARENT_SETTID) && ptid != 0 {
+			let mut buffers = translated_byte_buffer(pcb.memory_set.token(), ptid as *mut u8, core::mem::size_of::<u32>());
+			for (i, byte) in (new_pid as u32).to_ne_bytes().iter().enumerate() {
+				buffers[0][i] = *byte;
+			}
+		}
+		if flags.contains(CloneFlags::CLONE_CHILD_CLEARTID) && ctid != 0 {
+			new_pcb.clear_child_tid = Some(VirtAddr::from(ctid));
+		}
+
 		let new_proc=Arc::new(Process::new(new_pcb));
-		pcb.children.alive.insert(new_pid, new_proc.clone());
-		
+		if flags.contains(CloneFlags::CLONE_THREAD) {
+			pcb.thread_group.lock().push(new_pid);
+		} else {
+			pcb.children.alive.insert(new_pid, new_proc.clone());
+		}
+
 		let (r,t)=async_task::spawn(user_loop(Arc::new(Thread::new(new_proc.clone()))), |runnable|{TASK_QUEUE.push(runnable);});
 		r.schedule();
 		t.detach();
@@ -192,27 +473,64 @@ impl Thread{
 			argc+=1;
 		}
 
-		if path.ends_with(".sh"){
-			argvs.insert(0, "sh".to_string());
-			argvs.insert(0, "busybox".to_string());
-			path="/busybox".to_string();
-		}
-
-		if let Some(inode)=GLOBAL_DENTRY_CACHE.get(&path){
-			let mut data=inode.lock();
-			let data=data.file_data();
-			return match ElfFile::new(&data[..]){
-				Ok(elf_file)=> self.exec_from_elf(&elf_file, argvs),
-				Err(e)=> {
-					println!("[execve] {} : exec error.", path);
+		// Follow a `#!`-interpreter chain the way Linux `execve` does, instead
+		// of special-casing `.sh` as `busybox sh`. `MAX_INTERP_DEPTH` bounds a
+		// shebang pointing at another shebang script (or at itself) looping.
+		const MAX_INTERP_DEPTH: usize = 4;
+		let mut data: Vec<u8> = Vec::new();
+		for _ in 0..MAX_INTERP_DEPTH {
+			let inode = match GLOBAL_DENTRY_CACHE.get(&path) {
+				Some(inode) => inode,
+				None => {
+					println!("[execve] {} : not found.", path);
 					self.sys_exit(-1);
-					-1
-				},
+					return -1;
+				}
+			};
+			data = inode.lock().file_data().clone();
+
+			if !data.starts_with(b"#!") {
+				break;
 			}
-		}else{
-			println!("[execve] {} : not found.", path);
-			self.sys_exit(-1);
-			return -1;
+
+			let line_end = data.iter().position(|&b| b == b'\n').unwrap_or(data.len());
+			let line = core::str::from_utf8(&data[2..line_end]).unwrap_or("").trim();
+			let mut parts = line.splitn(2, ' ');
+			let interp = parts.next().unwrap_or("").trim();
+			let interp_arg = parts.next().map(str::trim).filter(|s| !s.is_empty());
+
+			if interp.is_empty() {
+				println!("[execve] {} : bad interpreter.", path);
+				self.sys_exit(-1);
+				return -1;
+			}
+
+			let (idir, iname) = self.get_abs_path(interp.to_string());
+			let interp_path = format!("{}{}", idir, iname);
+
+			let mut new_argvs: Vec<String> = Vec::new();
+			new_argvs.push(interp_path.clone());
+			if let Some(interp_arg) = interp_arg {
+				new_argvs.push(interp_arg.to_string());
+			}
+			new_argvs.push(path.clone());
+			new_argvs.extend(argvs.into_iter().skip(1));
+
+			path = interp_path;
+			argvs = new_argvs;
+		}
+
+		// Close every fd marked FD_CLOEXEC before the image is replaced, so
+		// those fds don't leak into the exec'd program.
+		pcb.fd_manager.lock().handle_exec();
+
+		return match ElfFile::new(&data[..]){
+			Ok(elf_file)=> self.exec_from_elf(&elf_file, argvs),
+			Err(e)=> {
+				println!("[execve] {} : exec error.", path);
+				self.sys_exit(-1);
+				-1
+			},
 		}
 
 		// extern "C" {
@@ -240,57 +558,65 @@ impl Thread{
 		YieldFuture(false).await
 	}
 
-	pub async unsafe fn sys_waitpid(&self, pid: isize, status:UserPtr<isize,Out>, options: usize) -> isize {
-		let mut pcb_lock=self.proc.inner.lock();
-		let mut pcb=pcb_lock.deref_mut();
-		
-		if PRINT_SYSCALL {println!("[waitpid] {} is waiting {} ,flag={}.",pcb.pid,pid,options);}
-		let nowpid = pcb.pid;
-		if pcb.children.alive.len()+pcb.children.zombie.len() ==0 {
-			if options > 0{
-				return 0;
-			}
-			return -1;
-		}
-		if (pid == -1) {
-			loop {
-				let pid={
-					let mut children= &mut pcb.children.zombie;
-					self.proc.inner.force_unlock();
-						
-						while children.is_empty() {
-							if options > 0{
-								return 0;
-							}
-							Thread::async_yield().await;
-						}
-
-					let mut pcb_lock = self.proc.inner.lock();
-					let (pid,process) = children.first_key_value().unwrap();
-					if (status.as_usize() as usize != 0) {
-						let status=status.raw_ptr_mut();
-						*status = (process.inner.lock().exit_code << 8) | (0);
-					}
-					// println!("{} cleand {}",pcb.pid,*pid);
-					*pid
-				};
-				let mut children= &mut pcb.children.zombie;
-				children.remove_entry(&pid);
-				return pid as isize;
+	/// `wait4`/`waitpid`. `pid > 0` waits for that specific child, `pid == -1`
+	/// waits for any child, and `pid == 0`/`pid < -1` (wait for the caller's
+	/// process group / for group `-pid`) fall back to "any child" since this
+	/// kernel doesn't model process groups. Blocks until a match zombifies
+	/// unless `WNOHANG` is set, matching real `wait4` rather than the old
+	/// "any nonzero `options` means don't block". `WNOHANG` is the only
+	/// option that changes anything: `WUNTRACED`, `WCONTINUED` and `__WALL`
+	/// are accepted and ignored, and `CLONE_THREAD` tasks are never among
+	/// the children searched.
+	pub async unsafe fn sys_waitpid(&self, pid: isize, status: UserPtr<isize, Out>, options: usize, rusage: UserPtr<RUsage, Out>) -> isize {
+		let options = WaitOption::from_bits_truncate(options as u32);
+		if PRINT_SYSCALL {println!("[waitpid] {} is waiting {} ,flag={:?}.",self.proc.pid,pid,options);}
+
+		loop {
+			let mut pcb = self.proc.inner.lock();
+			if pcb.children.alive.len() + pcb.children.zombie.len() == 0 {
+				return -1;
 			}
-		} else {
-			let mut children= &mut pcb.children.zombie;
-			if let Some(process) = children.get(&(pid as usize)){
-				if (status.as_usize() as usize != 0) {
-					let status=status.raw_ptr_mut();
-					*status = (process.inner.lock().exit_code << 8) | (0);
+
+			let found_pid = if pid > 0 {
+				pcb.children.zombie.contains_key(&(pid as usize)).then(|| pid as usize)
+			} else {
+				pcb.children.zombie.keys().next().copied()
+			};
+
+			if let Some(found_pid) = found_pid {
+				let process = pcb.children.zombie.remove(&found_pid).unwrap();
+				let child = process.inner.lock();
+				if status.as_usize() != 0 {
+					*status.raw_ptr_mut() = encode_wait_status(child.exit_code, child.state);
+				}
+				if rusage.as_usize() != 0 {
+					*rusage.raw_ptr_mut() = RUsage {
+						ru_utime: TimeVal::from_ticks(child.utime),
+						ru_stime: TimeVal::from_ticks(child.ktime),
+						// This kernel doesn't keep a separate resident-frame
+						// count per process, so approximate maxrss with the
+						// child's heap break (in KB, matching real rusage)
+						// rather than leaving it zeroed.
+						ru_maxrss: (child.heap_pos.0 / 1024) as isize,
+						..Default::default()
+					};
 				}
-				children.remove(&(pid as usize) );
-			}else{
+				return found_pid as isize;
+			}
+
+			// That specific child doesn't exist among either the alive or the
+			// zombie set, and never will: report ECHILD instead of blocking.
+			if pid > 0 && !pcb.children.alive.contains_key(&(pid as usize)) {
 				return -1;
 			}
+
+			if options.contains(WaitOption::WNOHANG) {
+				return 0;
+			}
+
+			drop(pcb);
+			Thread::async_yield().await;
 		}
-		0
 	}
 }
 
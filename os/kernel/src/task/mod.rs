@@ -1,9 +1,10 @@
 use crate::{fs::{
-    file::{OpenFlags, RegFileINode, TerminalINode},
+    file::{OpenFlags, PipeINode, RegFileINode, TerminalINode},
     vfs::INode,
 }, mm::{PhysAddr, VirtAddr}};
 use alloc::string::{String, ToString};
 use core::arch::global_asm;
+use core::sync::atomic::{AtomicUsize, Ordering};
 use hashbrown::HashMap;
 
 pub use context::ProcessContext;
@@ -36,6 +37,12 @@ lazy_static! {
     pub static ref global_open_file_table: GlobalOpenFileTable = GlobalOpenFileTable {
         table: Arc::new(Mutex::new(Vec::new())),
     };
+    pub static ref global_buffer_list: GlobalBufferList = GlobalBufferList {
+        table: Arc::new(Mutex::new(Vec::new())),
+    };
+    pub static ref global_futex_table: GlobalFutexTable = GlobalFutexTable {
+        table: Arc::new(Mutex::new(HashMap::new())),
+    };
 }
 
 #[derive(Copy, Clone, PartialEq)]
@@ -46,17 +53,155 @@ pub enum ProcessState {
     KILLED,
     EMPTY,
 }
-#[derive(Clone)]
+/// Fixed capacity of a pipe's backing ring buffer. Matches Linux's default
+/// pipe capacity of 16 pages.
+pub const PIPE_BUFFER_CAPACITY: usize = 64 * 1024;
+
+/// Bounded ring buffer backing one pipe, shared by every fd `dup`'d from
+/// either end. `readers`/`writers` count how many live `OpenFile`s (each
+/// shared by every fd `dup`'d from it) still reference each end, so a read
+/// on a buffer with no writers left can report EOF and a
+/// write to a buffer with no readers left can report `EPIPE`.
+pub struct PipeBuffer {
+    data: Vec<u8>,
+    head: usize,
+    len: usize,
+    pub readers: usize,
+    pub writers: usize,
+}
+
+impl PipeBuffer {
+    pub fn new() -> Self {
+        Self {
+            data: vec![0; PIPE_BUFFER_CAPACITY],
+            head: 0,
+            len: 0,
+            readers: 1,
+            writers: 1,
+        }
+    }
+
+    fn capacity(&self) -> usize {
+        self.data.len()
+    }
+
+    pub fn is_full(&self) -> bool {
+        self.len == self.capacity()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+
+    /// Copy as much of `bytes` as currently fits, returning the amount written.
+    pub fn try_write(&mut self, bytes: &[u8]) -> usize {
+        let free = self.capacity() - self.len;
+        let n = bytes.len().min(free);
+        let tail = (self.head + self.len) % self.capacity();
+        for (i, byte) in bytes[..n].iter().enumerate() {
+            self.data[(tail + i) % self.capacity()] = *byte;
+        }
+        self.len += n;
+        n
+    }
+
+    /// Copy out as many available bytes as `out` can hold, returning the amount read.
+    pub fn try_read(&mut self, out: &mut [u8]) -> usize {
+        let n = out.len().min(self.len);
+        for (i, slot) in out.iter_mut().enumerate().take(n) {
+            *slot = self.data[(self.head + i) % self.capacity()];
+        }
+        self.head = (self.head + n) % self.capacity();
+        self.len -= n;
+        n
+    }
+}
+
+pub struct GlobalBufferList {
+    table: Arc<Mutex<Vec<Arc<Mutex<PipeBuffer>>>>>,
+}
+
+impl GlobalBufferList {
+    pub fn insert(&self, buf: Arc<Mutex<PipeBuffer>>) {
+        self.table.lock().push(buf);
+    }
+}
+
+/// Wake counters for `futex(2)`, keyed by the *physical* address of the
+/// futex word so two mappings of the same page (e.g. `CLONE_VM` siblings
+/// translating the same tid word through their own page tables) share a
+/// counter instead of getting one queue per virtual address. The actual
+/// wait loop lives in `syscall::process::sys_futex`, which polls
+/// `wake_count` cooperatively; this table only records that a wake happened.
+pub struct GlobalFutexTable {
+    table: Arc<Mutex<HashMap<PhysAddr, usize>>>,
+}
+
+impl GlobalFutexTable {
+    /// Number of `FUTEX_WAKE`s ever recorded against `addr`, `0` if none.
+    pub fn wake_count(&self, addr: PhysAddr) -> usize {
+        *self.table.lock().get(&addr).unwrap_or(&0)
+    }
+
+    /// `FUTEX_WAKE`: bump `addr`'s wake count. Every waiter polling
+    /// `wake_count` on `addr` notices it, so this wakes all of them, not
+    /// just one.
+    pub fn wake(&self, addr: PhysAddr) {
+        let mut table = self.table.lock();
+        *table.entry(addr).or_insert(0) += 1;
+    }
+}
+
+/// Which side of a pipe an `OpenFile` is. The only thing this decides is
+/// which half of the shared `PipeBuffer`'s refcount `Drop` releases.
+#[derive(Clone, Copy, PartialEq, Eq)]
+pub enum PipeEnd {
+    Read,
+    Write,
+}
+
 pub struct OpenFile {
-    pub offset: usize,
+    /// current file position; shared by every `FileDescriptor` that was
+    /// `dup`'d from the same open file, so it lives behind an atomic rather
+    /// than being copied around by value.
+    pub offset: AtomicUsize,
     pub status_flags: u32,
     pub inode: Arc<Mutex<dyn INode>>,
+    /// `Some` for a pipe end, sharing the ring buffer with its sibling end.
+    /// Every `FileDescriptor` referencing a pipe end holds the *same*
+    /// `Arc<OpenFile>` (see `sys_dup`/`sys_dup3`/`fcntl(F_DUPFD)`), so the
+    /// `Arc`'s own refcount already tracks how many fds are open on this
+    /// end; `Drop` below turns "last fd closed" into a `PipeBuffer` update.
+    pub pipe: Option<(Arc<Mutex<PipeBuffer>>, PipeEnd)>,
+}
+
+impl Clone for OpenFile {
+    fn clone(&self) -> Self {
+        Self {
+            offset: AtomicUsize::new(self.offset.load(Ordering::Relaxed)),
+            status_flags: self.status_flags,
+            inode: self.inode.clone(),
+            pipe: self.pipe.clone(),
+        }
+    }
+}
+
+impl Drop for OpenFile {
+    fn drop(&mut self) {
+        if let Some((buffer, end)) = &self.pipe {
+            let mut buffer = buffer.lock();
+            match end {
+                PipeEnd::Read => buffer.readers = buffer.readers.saturating_sub(1),
+                PipeEnd::Write => buffer.writers = buffer.writers.saturating_sub(1),
+            }
+        }
+    }
 }
 
 impl OpenFile {
     pub fn new() -> Self {
         Self {
-            offset: 0,
+            offset: AtomicUsize::new(0),
             status_flags: 0,
             inode: Arc::new(Mutex::new(RegFileINode::new(
                 "/".to_string(),
@@ -65,42 +210,72 @@ impl OpenFile {
                 true,
                 true,
             ))),
+            pipe: None,
         }
     }
 
     pub fn new_stdin() -> Self {
         Self {
-            offset: 0,
+            offset: AtomicUsize::new(0),
             status_flags: 0,
             inode: Arc::new(Mutex::new(TerminalINode::new_stdin())),
+            pipe: None,
         }
     }
 
     pub fn new_stdout() -> Self {
         Self {
-            offset: 0,
+            offset: AtomicUsize::new(0),
             status_flags: 0,
             inode: Arc::new(Mutex::new(TerminalINode::new_stdout())),
+            pipe: None,
         }
     }
 
     pub fn new_stderr() -> Self {
         Self {
-            offset: 0,
+            offset: AtomicUsize::new(0),
             status_flags: 0,
             inode: Arc::new(Mutex::new(TerminalINode::new_stderr())),
+            pipe: None,
+        }
+    }
+
+    /// The read end of a new pipe sharing `buf` with its write end.
+    pub fn new_pipe_read(buf: Arc<Mutex<PipeBuffer>>) -> Self {
+        Self {
+            offset: AtomicUsize::new(0),
+            status_flags: 0,
+            inode: Arc::new(Mutex::new(PipeINode::new())),
+            pipe: Some((buf, PipeEnd::Read)),
+        }
+    }
+
+    /// The write end of a new pipe sharing `buf` with its read end.
+    pub fn new_pipe_write(buf: Arc<Mutex<PipeBuffer>>) -> Self {
+        Self {
+            offset: AtomicUsize::new(0),
+            status_flags: 0,
+            inode: Arc::new(Mutex::new(PipeINode::new())),
+            pipe: Some((buf, PipeEnd::Write)),
         }
     }
 }
 pub struct GlobalOpenFileTable {
-    table: Arc<Mutex<Vec<OpenFile>>>,
+    table: Arc<Mutex<Vec<Arc<OpenFile>>>>,
 }
 
 impl GlobalOpenFileTable {
+    /// Register `open_file` without breaking the `Arc` identity that
+    /// `FdManager::dup`/`dup2` rely on to share one seek offset: the
+    /// previous `(*open_file).clone()` pushed a copy into the table and
+    /// handed back a *different* `Arc` wrapping another copy, so no two
+    /// callers ever actually shared state. Pushing and returning the same
+    /// `Arc` keeps every holder pointed at one `OpenFile`.
     pub fn insert(&self, open_file: Arc<OpenFile>) -> Arc<OpenFile> {
         let mut table = self.table.lock();
-        table.push((*open_file).clone());
-        Arc::new(table.last().unwrap().clone())
+        table.push(open_file.clone());
+        open_file
     }
 }
 #[derive(Clone)]
@@ -108,6 +283,9 @@ pub struct FileDescriptor {
     pub open_file: Arc<OpenFile>,
     pub readable: bool,
     pub writable: bool,
+    /// `FD_CLOEXEC`: close this descriptor across `exec`, instead of letting
+    /// it leak into the replaced image.
+    pub cloexec: bool,
 }
 
 pub struct FdManager {
@@ -122,30 +300,36 @@ impl FdManager {
             open_file: Arc::new(OpenFile::new_stdin()),
             readable: true,
             writable: false,
+            cloexec: false,
         });
         v.push(FileDescriptor {
             open_file: Arc::new(OpenFile::new_stdout()),
             readable: false,
             writable: true,
+            cloexec: false,
         });
         v.push(FileDescriptor {
             open_file: Arc::new(OpenFile::new_stderr()),
             readable: false,
             writable: true,
+            cloexec: false,
         });
         Self { fd_array: v }
     }
     pub fn len(&self) -> usize {
         self.fd_array.len()
     }
+    /// `close(2)`: drop this descriptor's `OpenFile` reference, freeing the
+    /// slot for reuse. A no-op on an already-closed fd, not (as the
+    /// inverted check here used to read) on every *open* one — which left
+    /// every real fd, and the inode it held open, leaked forever.
     pub fn close(&mut self, fd: usize) {
-        let mut fd: Option<&mut FileDescriptor> = self.fd_array.get_mut(fd);
-        if let Some(fd) = fd {
-            if fd.readable || fd.writable {
-                // Do nothing
+        if let Some(fd) = self.fd_array.get_mut(fd) {
+            if !fd.readable && !fd.writable {
                 return;
             }
-            let open_file = fd.open_file.clone();
+            fd.readable = false;
+            fd.writable = false;
             fd.open_file = Arc::new(OpenFile::new());
         }
     }
@@ -153,6 +337,71 @@ impl FdManager {
         self.fd_array.push(file_descriptor);
         self.fd_array.len() - 1
     }
+    /// `dup(2)`: install a new descriptor in the lowest free slot sharing
+    /// `oldfd`'s `Arc<OpenFile>`, so both fds advance the same seek offset
+    /// and a pipe end's refcount (tracked by `OpenFile::drop`) still counts
+    /// both. Returns the new fd, or `usize::MAX` if `oldfd` isn't open.
+    pub fn dup(&mut self, oldfd: usize) -> usize {
+        let old = match self.fd_array.get(oldfd) {
+            Some(old) if old.readable || old.writable => old.clone(),
+            _ => return usize::MAX,
+        };
+        let new_fd = self.lowest_free_fd(0);
+        let new_descriptor = FileDescriptor {
+            readable: old.readable,
+            writable: old.writable,
+            open_file: old.open_file,
+            cloexec: false,
+        };
+        if new_fd >= self.fd_array.len() {
+            self.fd_array.push(new_descriptor);
+        } else {
+            self.fd_array[new_fd] = new_descriptor;
+        }
+        new_fd
+    }
+    /// `dup2(2)`: make `newfd` alias `oldfd`'s `Arc<OpenFile>`, first
+    /// closing whatever `newfd` held (overwriting the slot drops the old
+    /// `FileDescriptor`, releasing its `OpenFile` exactly as `close` does).
+    /// `newfd == oldfd` is a no-op, since overwriting the slot in that case
+    /// would clear the descriptor's close-on-exec flag.
+    pub fn dup2(&mut self, oldfd: usize, newfd: usize) -> isize {
+        let old = match self.fd_array.get(oldfd) {
+            Some(old) if old.readable || old.writable => old.clone(),
+            _ => return -1,
+        };
+        if oldfd == newfd {
+            return newfd as isize;
+        }
+        while newfd >= self.fd_array.len() {
+            self.fd_array.push(FileDescriptor {
+                readable: false,
+                writable: false,
+                open_file: Arc::new(OpenFile::new()),
+                cloexec: false,
+            });
+        }
+        self.fd_array[newfd] = FileDescriptor {
+            readable: old.readable,
+            writable: old.writable,
+            open_file: old.open_file,
+            cloexec: false,
+        };
+        newfd as isize
+    }
+    /// `FD_CLOEXEC`/`fcntl(F_SETFD)`: mark (or clear) this fd to be closed
+    /// across the next `execve`. No-op if `fd` isn't open.
+    pub fn set_cloexec(&mut self, fd: usize, cloexec: bool) {
+        if let Some(fd) = self.fd_array.get_mut(fd) {
+            if fd.readable || fd.writable {
+                fd.cloexec = cloexec;
+            }
+        }
+    }
+    /// `fcntl(F_GETFD)`: whether this fd is marked close-on-exec.
+    pub fn get_cloexec(&self, fd: usize) -> Option<bool> {
+        self.fd_array.get(fd).map(|fd| fd.cloexec)
+    }
     pub fn get(&self, fd: usize) -> Option<&FileDescriptor> {
         self.fd_array.get(fd)
     }
@@ -162,6 +411,33 @@ impl FdManager {
     pub fn remove(&mut self, fd: usize) -> FileDescriptor {
         self.fd_array.remove(fd)
     }
+    /// Find the lowest-numbered fd `>= min_fd` that isn't in use yet.
+    pub fn lowest_free_fd(&self, min_fd: usize) -> usize {
+        let mut fd = min_fd;
+        while fd < self.fd_array.len() && (self.fd_array[fd].readable || self.fd_array[fd].writable) {
+            fd += 1;
+        }
+        fd
+    }
+    /// Close every descriptor marked `FD_CLOEXEC`, as `execve` must.
+    pub fn handle_exec(&mut self) {
+        for fd in 0..self.fd_array.len() {
+            if self.fd_array[fd].cloexec {
+                self.fd_array[fd] = FileDescriptor {
+                    open_file: Arc::new(OpenFile::new()),
+                    readable: false,
+                    writable: false,
+                    cloexec: false,
+                };
+            }
+        }
+    }
+    /// Close every descriptor, as task teardown must. Emptying `fd_array`
+    /// drops each `Arc<OpenFile>`; for a pipe end whose last reference this
+    /// was, `OpenFile`'s `Drop` releases its `PipeBuffer` reader/writer slot.
+    pub fn close_all(&mut self) {
+        self.fd_array.clear();
+    }
 }
 pub struct GlobalInodeTable {
     pub table: Arc<Mutex<Vec<Arc<Mutex<dyn INode>>>>>,
@@ -185,6 +461,24 @@ impl GlobalDentryCache {
         table.insert(path.to_string(), inode);
         table.get(&old_path).unwrap().clone()
     }
+    /// Remove the dentry at `path`, returning its inode if one was present.
+    /// Used by `rename`/`renameat2` to move an entry to a new path.
+    pub fn remove(&self, path: &str) -> Option<Arc<Mutex<dyn INode>>> {
+        self.table.lock().remove(path)
+    }
+    /// Number of dentries currently cached; used to fill `f_files` in `statfs`.
+    pub fn len(&self) -> usize {
+        self.table.lock().len()
+    }
+    /// Sum of `file_size()` across every cached inode; used to fill the
+    /// used-space fields of `statfs`.
+    pub fn total_bytes(&self) -> usize {
+        self.table
+            .lock()
+            .values()
+            .map(|inode| inode.lock().file_size() as usize)
+            .sum()
+    }
 }
 
 pub struct PCB {
@@ -201,6 +495,10 @@ pub struct PCB {
     pub ktime: usize,
     pub cwd: String,
     pub fd_manager: Arc<Mutex<FdManager>>,
+    /// Set by signal delivery to kick this task out of a blocking wait loop
+    /// (e.g. the pipe read/write loops in `syscall::fs`) without it having
+    /// to poll for a specific signal; cleared by whoever consumes it.
+    pub interrupted: bool,
 }
 
 impl PCB {
@@ -219,8 +517,15 @@ impl PCB {
             ktime: 0,
             cwd: "/".to_string(),
             fd_manager: Arc::new(Mutex::new(FdManager::new())),
+            interrupted: false,
         }
     }
+
+    /// Consume and clear the interrupted flag, as an `EINTR`-style retry
+    /// loop should before deciding whether to restart or return partial data.
+    pub fn take_interrupted(&mut self) -> bool {
+        core::mem::replace(&mut self.interrupted, false)
+    }
 }
 
 pub fn myproc() -> &'static mut PCB {